@@ -0,0 +1,234 @@
+extern crate lox_rs;
+
+use lox_rs::lox::ast::{Ast, Expr, ExprId, FunctionId, Interpreter, Stmt, StmtId};
+use lox_rs::lox::atom::Atom;
+use lox_rs::lox::parser::Parser;
+use lox_rs::lox::resolver;
+use lox_rs::lox::scanner::{Scanner, TokenType};
+use lox_rs::lox::symbol::Symbol;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+//counts the heap allocations made while interpreting each program, to
+//catch the interpreter allocating per node again
+struct Counting;
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const PROGRAMS: &[(&str, &str)] = &[
+    ("fib", "fun fib(n) { return n < 2 ? n : fib(n - 1) + fib(n - 2); } var r = fib(18);"),
+    ("arith", "var x = 1; fun f(n) { return n == 0 ? x : f(n - 1) + (x * 2 - x / 2) % 7; } var r = f(500);"),
+];
+
+//the baseline: the tree as it was before the arena, boxed nodes that the
+//evaluator clones out of their parent before every visit
+#[derive(Clone)]
+enum Boxed {
+    Literal(Atom),
+    Var(Symbol),
+    Grouping(Box<Boxed>),
+    Binary(Box<Boxed>, TokenType, Box<Boxed>),
+    Conditional(Box<Boxed>, Box<Boxed>, Box<Boxed>),
+    Call(Box<Boxed>, Vec<Boxed>)
+}
+
+#[derive(Clone)]
+enum BoxedStmt {
+    Expr(Box<Boxed>),
+    Return(Box<Boxed>),
+    Var(Symbol, Box<Boxed>),
+    Function(Symbol, Rc<BoxedFunction>)
+}
+
+struct BoxedFunction {
+    params: Vec<Symbol>,
+    body: Vec<BoxedStmt>
+}
+
+fn boxed(ast: &Ast, expr: ExprId) -> Boxed {
+    match ast.expr(expr) {
+        Expr::Literal(atom) => Boxed::Literal(atom.clone()),
+        Expr::Var(name) => Boxed::Var(name.clone()),
+        Expr::Grouping(expr) => Boxed::Grouping(Box::new(boxed(ast, *expr))),
+        Expr::Binary(lhs, op, rhs) => Boxed::Binary(Box::new(boxed(ast, *lhs)), op.token_type.clone(), Box::new(boxed(ast, *rhs))),
+        Expr::Conditional(cond, then, otherwise) => Boxed::Conditional(Box::new(boxed(ast, *cond)), Box::new(boxed(ast, *then)), Box::new(boxed(ast, *otherwise))),
+        Expr::Call(callee, _, args) => Boxed::Call(Box::new(boxed(ast, *callee)), args.iter().map(|arg| boxed(ast, *arg)).collect()),
+        _ => unimplemented!("not used by the benchmark programs")
+    }
+}
+
+fn boxed_stmt(ast: &Ast, stmt: StmtId) -> BoxedStmt {
+    match ast.stmt(stmt) {
+        Stmt::Expr(expr) => BoxedStmt::Expr(Box::new(boxed(ast, *expr))),
+        Stmt::Return(expr) => BoxedStmt::Return(Box::new(boxed(ast, *expr))),
+        Stmt::Var(name, init, _) => BoxedStmt::Var(name.clone(), Box::new(boxed(ast, *init))),
+        Stmt::Function(decl, _) => {
+            let name = ast.function(*decl).name.clone().unwrap();
+            BoxedStmt::Function(name, Rc::new(boxed_function(ast, *decl)))
+        },
+        _ => unimplemented!("not used by the benchmark programs")
+    }
+}
+
+fn boxed_function(ast: &Ast, decl: FunctionId) -> BoxedFunction {
+    let decl = ast.function(decl);
+    BoxedFunction { params: decl.params.clone(), body: decl.body.iter().map(|stmt| boxed_stmt(ast, *stmt)).collect() }
+}
+
+#[derive(Clone)]
+enum Value {
+    Atom(Atom),
+    Function(Rc<BoxedFunction>, Rc<RefCell<Env>>)
+}
+
+impl Value {
+    fn atom(self) -> Atom {
+        match self {
+            Value::Atom(atom) => atom,
+            Value::Function(..) => panic!("not a value")
+        }
+    }
+}
+
+struct Env {
+    values: HashMap<Symbol, Value>,
+    enclosing: Option<Rc<RefCell<Env>>>
+}
+
+impl Env {
+    fn get(&self, name: &Symbol) -> Value {
+        match (self.values.get(name), &self.enclosing) {
+            (Some(value), _) => value.clone(),
+            (None, Some(enclosing)) => enclosing.borrow().get(name),
+            (None, None) => panic!("{} not defined", name)
+        }
+    }
+}
+
+struct BoxedInterpreter {
+    env: Rc<RefCell<Env>>,
+    returning: Option<Value>
+}
+
+impl BoxedInterpreter {
+    fn execute(&mut self, stmt: &BoxedStmt) {
+        match stmt {
+            BoxedStmt::Expr(expr) => {self.evaluate(*expr.clone());},
+            BoxedStmt::Return(expr) => self.returning = Some(self.evaluate(*expr.clone())),
+            BoxedStmt::Var(name, init) => {
+                let value = self.evaluate(*init.clone());
+                self.env.borrow_mut().values.insert(name.clone(), value);
+            },
+            BoxedStmt::Function(name, decl) => {
+                let fun = Value::Function(decl.clone(), self.env.clone());
+                self.env.borrow_mut().values.insert(name.clone(), fun);
+            }
+        }
+    }
+
+    fn evaluate(&mut self, expr: Boxed) -> Value {
+        match expr {
+            Boxed::Literal(atom) => Value::Atom(atom),
+            Boxed::Var(name) => self.env.borrow().get(&name),
+            Boxed::Grouping(expr) => self.evaluate(*expr.clone()),
+            Boxed::Conditional(cond, then, otherwise) => {
+                if self.evaluate(*cond.clone()).atom().is_truthy() {
+                    self.evaluate(*then.clone())
+                }else{
+                    self.evaluate(*otherwise.clone())
+                }
+            },
+            Boxed::Binary(lhs, op, rhs) => {
+                let lhs = self.evaluate(*lhs.clone()).atom();
+                let rhs = self.evaluate(*rhs.clone()).atom();
+                Value::Atom(match op {
+                    TokenType::PLUS => Atom::add(lhs, rhs),
+                    TokenType::MINUS => Atom::sub(lhs, rhs),
+                    TokenType::STAR => Atom::mult(lhs, rhs),
+                    TokenType::SLASH => Atom::divide(lhs, rhs),
+                    TokenType::PERCENT => Atom::modulo(lhs, rhs),
+                    TokenType::EQUALEQUAL => Ok(Atom::Bool(lhs == rhs)),
+                    op => Atom::comp(&op, lhs, rhs)
+                }.unwrap())
+            },
+            Boxed::Call(callee, args) => {
+                let callee = self.evaluate(*callee.clone());
+                let args: Vec<Value> = args.iter().map(|arg| self.evaluate(arg.clone())).collect();
+                match callee {
+                    Value::Function(decl, closure) => {
+                        let mut values = HashMap::new();
+                        for (param, arg) in decl.params.iter().zip(args) {
+                            values.insert(param.clone(), arg);
+                        }
+                        let env = Rc::new(RefCell::new(Env { values, enclosing: Some(closure) }));
+                        let previous = std::mem::replace(&mut self.env, env);
+                        for stmt in decl.body.iter() {
+                            if self.returning.is_some() {
+                                break;
+                            }
+                            self.execute(stmt);
+                        }
+                        self.env = previous;
+                        self.returning.take().unwrap_or(Value::Atom(Atom::Nil))
+                    },
+                    Value::Atom(atom) => panic!("{} is not callable", atom)
+                }
+            }
+        }
+    }
+}
+
+//runs f and returns the allocations, bytes and time it took
+fn measure(f: impl FnOnce()) -> (usize, usize, Duration) {
+    let allocs = ALLOCS.load(Ordering::Relaxed);
+    let bytes = BYTES.load(Ordering::Relaxed);
+    let start = Instant::now();
+    f();
+    (ALLOCS.load(Ordering::Relaxed) - allocs, BYTES.load(Ordering::Relaxed) - bytes, start.elapsed())
+}
+
+fn main() {
+    println!("{:<6} {:<6} {:>11} {:>13} {:>10}", "", "", "allocations", "bytes", "time");
+    for (name, source) in PROGRAMS {
+        let mut scanner = Scanner::new(source.to_string());
+        let tokens = scanner.scan_tokens().unwrap().clone();
+        let mut ast = Ast::default();
+        let stmts = Parser::new(&tokens, &mut ast).parse().unwrap();
+        let program: Vec<BoxedStmt> = stmts.iter().map(|stmt| boxed_stmt(&ast, *stmt)).collect();
+        resolver::resolve(&mut ast, &stmts).unwrap();
+        let ast = Rc::new(ast);
+
+        let mut intp = Interpreter::default();
+        let arena = measure(|| {intp.interpret(&ast, &stmts);});
+
+        let mut intp = BoxedInterpreter { env: Rc::new(RefCell::new(Env { values: HashMap::new(), enclosing: None })), returning: None };
+        let boxed = measure(|| {
+            for stmt in program.iter() {
+                intp.execute(stmt);
+            }
+        });
+
+        for (kind, (allocs, bytes, time)) in [("arena", arena), ("boxed", boxed)] {
+            println!("{:<6} {:<6} {:>11} {:>13} {:>10.2?}", name, kind, allocs, bytes, time);
+        }
+    }
+}
@@ -1,15 +1,21 @@
 extern crate lox_rs;
 use lox_rs::lox;
 
-use lox::ast::{Expr, AstPrinter, ExprVisitor};
+use lox::ast::{Ast, Expr, AstPrinter, ExprVisitor, Span};
 use lox::scanner::{Token, TokenType};
+use lox::atom::Atom;
 
 fn main() {
-    let expr = Expr::Binary(
-                        Box::new(Expr::Unary(Box::new(Token { token_type: TokenType::MINUS, lexeme: "-".into(), line: 55 }), Box::new(Expr::NumberLiteral(3.14)))),
-                        Box::new(Token{ token_type: TokenType::STAR, lexeme: "*".into(), line:13 }),
-                        Box::new(Expr::Grouping(Box::new(Expr::NumberLiteral(52.0)))));
+    let mut ast = Ast::default();
+    let two = ast.add_expr(Expr::Literal(Atom::Number(2.5)), Span::default());
+    let neg = ast.add_expr(Expr::Unary(Token { token_type: TokenType::MINUS, lexeme: "-".into(), line: 55, leading_trivia: String::new(), trailing_trivia: String::new() }, two), Span::default());
+    let lit = ast.add_expr(Expr::Literal(Atom::Number(52.0)), Span::default());
+    let group = ast.add_expr(Expr::Grouping(lit), Span::default());
+    let expr = ast.add_expr(Expr::Binary(
+                        neg,
+                        Token{ token_type: TokenType::STAR, lexeme: "*".into(), line:13, leading_trivia: String::new(), trailing_trivia: String::new() },
+                        group), Span::default());
 
-        println!("{}", AstPrinter{}.visit_expr(&expr));
+        println!("{}", AstPrinter::new(&ast).visit_expr(expr));
                           
 }
@@ -1,98 +1,599 @@
 use super::errors::LanguageError;
 use super::scanner::{Token, TokenType};
 use super::atom::Atom;
+use super::native::{self, IntoNative};
+use super::output::Shared;
+use super::sandbox::Sandbox;
+use super::trace::{Hook, Tracer};
+use super::symbol::Symbol;
 
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+//how deep evaluation may nest by default, each Lox call is a few levels
+pub const MAX_EVAL_DEPTH: usize = 1000;
 
 #[derive(Debug, Clone)]
 pub enum Expr {
-    Binary(Box<Expr>, Box<Token>, Box<Expr>),
-    Grouping(Box<Expr>),
+    //name, `=` or a compound assignment operator, value
+    Assign(Symbol, Token, ExprId),
+    Binary(ExprId, Token, ExprId),
+    //callee, closing paren, arguments
+    Call(ExprId, Token, Vec<ExprId>),
+    //object, property
+    Get(ExprId, Symbol),
+    //object, property, `=` or a compound assignment operator, value
+    Set(ExprId, Symbol, Token, ExprId),
+    Grouping(ExprId),
+    Lambda(FunctionId),
+    //condition, then, else
+    Conditional(ExprId, ExprId, ExprId),
     Literal(Atom),
-    Unary(Box<Token>, Box<Expr>),
-    Var(Box<String>)
+    Unary(Token, ExprId),
+    Var(Symbol)
 
 }
 
 #[derive(Debug, Clone)]
 pub enum Stmt {
-    Expr(Box<Expr>),
-    Print(Box<Expr>),
-    Var(Box<String>, Box<Expr>)
+    Block(Vec<StmtId>),
+    Expr(ExprId),
+    //declaration, doc comment
+    Function(FunctionId, Option<String>),
+    Print(ExprId),
+    Return(ExprId),
+    //name, initializer, doc comment
+    Var(Symbol, ExprId, Option<String>)
 }
 
+//shared by named functions, anonymous functions and arrow lambdas, an
+//arrow lambda's body is a single return statement
 #[derive(Debug, Clone)]
-pub struct VarDecl(Box<Expr>);
+pub struct FunctionDecl {
+    pub name: Option<Symbol>,
+    pub params: Vec<Symbol>,
+    pub body: Vec<StmtId>
+}
+
+//handles into an Ast, only meaningful for the Ast that handed them out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ExprId(u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StmtId(u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FunctionId(u32);
+
+//first and last source line of a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub line: i32,
+    pub end_line: i32
+}
+
+//every node of a parse lives here and refers to its children by id.
+//Anything computed about a node after parsing goes in a side table keyed
+//by its id rather than in the node itself
+#[derive(Debug, Default)]
+pub struct Ast {
+    exprs: Vec<Expr>,
+    stmts: Vec<Stmt>,
+    functions: Vec<FunctionDecl>,
+    expr_spans: Vec<Span>,
+    stmt_spans: Vec<Span>,
+    //how many scopes out from its use a local variable was declared,
+    //variables missing from here are globals
+    locals: HashMap<ExprId, usize>
+}
+
+impl Ast {
+    pub fn add_expr(&mut self, expr: Expr, span: Span) -> ExprId {
+        self.exprs.push(expr);
+        self.expr_spans.push(span);
+        ExprId(self.exprs.len() as u32 - 1)
+    }
+
+    pub fn add_stmt(&mut self, stmt: Stmt, span: Span) -> StmtId {
+        self.stmts.push(stmt);
+        self.stmt_spans.push(span);
+        StmtId(self.stmts.len() as u32 - 1)
+    }
+
+    pub fn add_function(&mut self, decl: FunctionDecl) -> FunctionId {
+        self.functions.push(decl);
+        FunctionId(self.functions.len() as u32 - 1)
+    }
+
+    pub fn expr(&self, id: ExprId) -> &Expr {
+        &self.exprs[id.0 as usize]
+    }
+
+    pub fn stmt(&self, id: StmtId) -> &Stmt {
+        &self.stmts[id.0 as usize]
+    }
+
+    pub fn function(&self, id: FunctionId) -> &FunctionDecl {
+        &self.functions[id.0 as usize]
+    }
+
+    pub fn expr_span(&self, id: ExprId) -> Span {
+        self.expr_spans[id.0 as usize]
+    }
+
+    pub fn stmt_span(&self, id: StmtId) -> Span {
+        self.stmt_spans[id.0 as usize]
+    }
+
+    pub fn resolve_local(&mut self, id: ExprId, depth: usize) {
+        self.locals.insert(id, depth);
+    }
+
+    pub fn local_depth(&self, id: ExprId) -> Option<usize> {
+        self.locals.get(&id).copied()
+    }
+}
+
+//a function value, closing over the environment it was created in. The
+//Ast is kept alive for as long as the function is
+pub struct Function {
+    pub ast: Rc<Ast>,
+    pub decl: FunctionId,
+    pub closure: Rc<RefCell<Environment>>
+}
+
+impl Function {
+    pub fn decl(&self) -> &FunctionDecl {
+        self.ast.function(self.decl)
+    }
+}
+
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.decl().name {
+            Some(name) => write!(f, "<fn {}>", name),
+            None => write!(f, "<fn>")
+        }
+    }
+}
 
 pub trait ExprVisitor<T> {
-    fn visit_expr(&mut self, exp: &Expr) -> T; 
+    fn visit_expr(&mut self, exp: ExprId) -> T; 
 }
 
 pub trait StmtVisitor {
-    fn visit_stmt(&mut self, stmt: &Stmt); 
+    fn visit_stmt(&mut self, stmt: StmtId); 
 }
 
 
-pub struct AstPrinter;
+pub struct AstPrinter<'a> {
+    ast: &'a Ast
+}
+
+impl<'a> AstPrinter<'a> {
+    pub fn new(ast: &'a Ast) -> Self {
+        AstPrinter { ast }
+    }
+
+    pub fn print_stmt(&mut self, stmt: StmtId) -> String {
+        match self.ast.stmt(stmt) {
+            Stmt::Block(stmts) => format!("(block {})", stmts.iter().map(|s| self.print_stmt(*s) + " ").collect::<String>()),
+            Stmt::Expr(e) => format!("(expr {} )", self.visit_expr(*e)),
+            Stmt::Function(decl, _) => self.print_function(*decl),
+            Stmt::Print(e) => format!("(print {} )", self.visit_expr(*e)),
+            Stmt::Return(e) => format!("(return {} )", self.visit_expr(*e)),
+            Stmt::Var(name, e, _) => format!("(var {} {} )", name, self.visit_expr(*e))
+        }
+    }
+
+    fn print_function(&mut self, decl: FunctionId) -> String {
+        let decl = self.ast.function(decl);
+        let name = decl.name.as_ref().map_or(String::new(), |name| format!("{} ", name));
+        let params: Vec<String> = decl.params.iter().map(|p| p.to_string()).collect();
+        let body: Vec<String> = decl.body.iter().map(|s| self.print_stmt(*s)).collect();
+        format!("(fun {}({}) {} )", name, params.join(" "), body.join(" "))
+    }
+}
 
-impl ExprVisitor<String> for AstPrinter {
-    fn visit_expr(&mut self, exp: &Expr) -> String {
-        match exp {
-            Expr::Binary(e1, t, e2) => format!("[({}) {} {}]", t, self.visit_expr(e1), self.visit_expr(e2)),
-            Expr::Grouping(e) => format!("(group {} )", self.visit_expr(e)),
+impl ExprVisitor<String> for AstPrinter<'_> {
+    fn visit_expr(&mut self, exp: ExprId) -> String {
+        match self.ast.expr(exp) {
+            Expr::Assign(name, t, e) => format!("({} {} {} )", t, name, self.visit_expr(*e)),
+            Expr::Binary(e1, t, e2) => format!("[({}) {} {}]", t, self.visit_expr(*e1), self.visit_expr(*e2)),
+            Expr::Call(c, _, args) => format!("(call {} {})", self.visit_expr(*c), args.iter().map(|a| self.visit_expr(*a) + " ").collect::<String>()),
+            Expr::Get(o, name) => format!("(. {} {} )", self.visit_expr(*o), name),
+            Expr::Set(o, name, t, e) => format!("({} (. {} {}) {} )", t, self.visit_expr(*o), name, self.visit_expr(*e)),
+            Expr::Grouping(e) => format!("(group {} )", self.visit_expr(*e)),
+            Expr::Lambda(decl) => self.print_function(*decl),
+            Expr::Conditional(c, t, e) => format!("(? {} {} {} )", self.visit_expr(*c), self.visit_expr(*t), self.visit_expr(*e)),
             Expr::Literal(n) => format!("{:?}", n),
-            Expr::Unary(t, e) => format!("( {} {} )", t, self.visit_expr(e)),
+            Expr::Unary(t, e) => format!("( {} {} )", t, self.visit_expr(*e)),
             Expr::Var(val) => format!("{}", val)
         }
     }
 }
 
 pub struct Environment {
-    values: HashMap<String, Atom>
+    values: HashMap<Symbol, Atom>,
+    enclosing: Option<Rc<RefCell<Environment>>>
 }
 
 impl Environment {
+    pub fn new(enclosing: Option<Rc<RefCell<Environment>>>) -> Self {
+        Environment { values: HashMap::new(), enclosing }
+    }
 
-    pub fn set(&mut self, lval: String, rval: Atom) {
+    pub fn set(&mut self, lval: Symbol, rval: Atom) {
         self.values.insert(lval, rval);
     }
-    pub fn get(&self, lval: String) -> Atom {
-        match self.values.get(&lval) {
-            Some(rval) => rval.clone(),
+
+    //the variables declared in this scope itself, sorted by name
+    pub fn vars(&self) -> Vec<(Symbol, Atom)> {
+        let mut vars: Vec<(Symbol, Atom)> = self.values.iter().map(|(name, value)| (name.clone(), value.clone())).collect();
+        vars.sort_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs));
+        vars
+    }
+    //assigning to a variable that was never declared is an error
+    pub fn assign(&mut self, lval: &Symbol, rval: Atom) -> Result<Atom, LanguageError> {
+        match (self.values.get_mut(lval), &self.enclosing) {
+            (Some(slot), _) => {
+                *slot = rval.clone();
+                Ok(rval)
+            },
+            (None, Some(enclosing)) => enclosing.borrow_mut().assign(lval, rval),
+            (None, None) => Err(LanguageError::RuntimeError(format!("Undefined variable {}", lval)))
+        }
+    }
+
+    pub fn get(&self, lval: &Symbol) -> Atom {
+        match self.lookup(lval) {
+            Some(rval) => rval,
             None => panic!("{} not defined", lval)
         }
     }
+
+    pub fn lookup(&self, lval: &Symbol) -> Option<Atom> {
+        match (self.values.get(lval), &self.enclosing) {
+            (Some(rval), _) => Some(rval.clone()),
+            (None, Some(enclosing)) => enclosing.borrow().lookup(lval),
+            (None, None) => None
+        }
+    }
+
+    //the resolver already worked out which environment holds a local
+    pub fn get_at(&self, depth: usize, lval: &Symbol) -> Atom {
+        match (depth, &self.enclosing) {
+            (0, _) => self.get(lval),
+            (_, Some(enclosing)) => enclosing.borrow().get_at(depth - 1, lval),
+            (_, None) => panic!("{} not defined", lval)
+        }
+    }
+
+    pub fn assign_at(&mut self, depth: usize, lval: &Symbol, rval: Atom) -> Result<Atom, LanguageError> {
+        match (depth, &self.enclosing) {
+            (0, _) => self.assign(lval, rval),
+            (_, Some(enclosing)) => enclosing.borrow_mut().assign_at(depth - 1, lval, rval),
+            (_, None) => Err(LanguageError::RuntimeError(format!("Undefined variable {}", lval)))
+        }
+    }
 }
 
 pub struct Interpreter {
     pub error: Option<LanguageError>,
-    pub env: Environment
+    pub env: Rc<RefCell<Environment>>,
+    pub globals: Rc<RefCell<Environment>>,
+    //the tree being walked, swapped for the callee's during a call
+    ast: Rc<Ast>,
+    //set by a return statement until the call it returns from picks it up
+    returning: Option<Atom>,
+    //how many expressions and blocks are being evaluated, calls included.
+    //Past max_depth evaluation stops with an error instead of overflowing
+    //the stack on runaway recursion
+    depth: usize,
+    pub max_depth: usize,
+    //limits for running untrusted code
+    pub sandbox: Sandbox,
+    //where print writes, and where diagnostics and traces go
+    stdout: Box<dyn Write>,
+    stderr: Shared,
+    hook: Option<Box<dyn Hook>>,
+    //the current error was already handed to the hook
+    error_traced: bool
 }
 
 impl Default for Interpreter {
-    fn default() -> Self { Interpreter { error: None , env: Environment{ values: HashMap::new() } }}
+    fn default() -> Self {
+        let globals = Rc::new(RefCell::new(Environment::new(None)));
+        let mut intp = Interpreter { error: None, env: globals.clone(), globals, ast: Rc::new(Ast::default()), returning: None, depth: 0, max_depth: MAX_EVAL_DEPTH, sandbox: Sandbox::default(),
+                                    stdout: Box::new(io::stdout()), stderr: Shared::new(io::stderr()), hook: None, error_traced: false };
+        //none of these take a Vec, so they can't be refused
+        intp.define_native("clock", native::clock).unwrap();
+        intp.define_native("str", native::stringify).unwrap();
+        intp.define_native("typeof", native::type_of).unwrap();
+        intp
+    }
 }
 
 impl Interpreter {
 
-    pub fn evaluate(&mut self, expr: Expr) -> Atom {
-        if let Some(_) = &self.error {
+    //runs top level statements of a resolved Ast, the value of the last
+    //one is returned when it is an expression statement
+    pub fn interpret(&mut self, ast: &Rc<Ast>, stmts: &[StmtId]) -> Atom {
+        self.ast = ast.clone();
+        self.sandbox.start();
+        let (last, stmts) = match stmts.split_last() {
+            Some((last, stmts)) => (*last, stmts),
+            None => return Atom::Nil
+        };
+
+        for stmt in stmts {
+            if self.error.is_some() {
+                return Atom::Nil;
+            }
+            self.execute(*stmt);
+        }
+        if self.error.is_some() {
             return Atom::Nil;
         }
+        match ast.stmt(last) {
+            Stmt::Expr(expr) if self.hook.is_some() => {
+                self.trace_stmt(last);
+                self.evaluate(*expr)
+            },
+            Stmt::Expr(expr) => self.evaluate(*expr),
+            _ => {
+                self.execute(last);
+                Atom::Nil
+            }
+        }
+    }
+
+    pub fn evaluate(&mut self, expr: ExprId) -> Atom {
+        if self.error.is_some() || !self.enter() {
+            return Atom::Nil;
+        }
+
+        let value = self.visit_expr(expr);
+        self.depth -= 1;
+        if self.hook.is_some() {
+            self.trace_expr(expr, &value);
+        }
+        value
+    }
+
+    fn execute(&mut self, stmt: StmtId) {
+        if self.hook.is_none() {
+            return self.visit_stmt(stmt);
+        }
+
+        self.trace_stmt(stmt);
+        self.visit_stmt(stmt);
+        let span = self.ast.stmt_span(stmt);
+        self.trace_error(span);
+    }
+
+    pub fn execute_block(&mut self, stmts: &[StmtId], env: Rc<RefCell<Environment>>) {
+        if !self.enter() {
+            return;
+        }
+
+        let previous = std::mem::replace(&mut self.env, env);
+        for stmt in stmts {
+            if self.error.is_some() || self.returning.is_some() {
+                break;
+            }
+            self.execute(*stmt);
+        }
+        self.env = previous;
+        self.depth -= 1;
+    }
+
+    //callbacks for every statement, expression, call and error from now
+    //on, see trace::Hook
+    pub fn set_hook(&mut self, hook: impl Hook + 'static) {
+        self.hook = Some(Box::new(hook));
+    }
 
-        self.visit_expr(&expr)
+    pub fn clear_hook(&mut self) {
+        self.hook = None;
+    }
+
+    fn trace_stmt(&mut self, stmt: StmtId) {
+        let ast = self.ast.clone();
+        if let Some(hook) = self.hook.as_mut() {
+            //nothing has failed yet when a statement starts
+            self.error_traced = false;
+            hook.statement(&ast, stmt, ast.stmt_span(stmt));
+        }
+    }
+
+    fn trace_expr(&mut self, expr: ExprId, value: &Atom) {
+        let ast = self.ast.clone();
+        let span = ast.expr_span(expr);
+        match self.hook.as_mut() {
+            Some(hook) if self.error.is_none() => hook.expression(&ast, expr, span, value),
+            _ => self.trace_error(span)
+        }
+    }
+
+    //the innermost node an error comes out of reports it
+    fn trace_error(&mut self, span: Span) {
+        if let (Some(hook), Some(err), false) = (self.hook.as_mut(), &self.error, self.error_traced) {
+            hook.error(err, span);
+            self.error_traced = true;
+        }
+    }
+
+    fn call_at(&mut self, span: Span, callee: Atom, args: Vec<Atom>) -> Result<Atom, LanguageError> {
+        if self.hook.is_none() {
+            return self.invoke(callee, args);
+        }
+
+        if let Some(hook) = self.hook.as_mut() {
+            hook.enter_function(&callee, &args, span);
+        }
+        let result = self.invoke(callee.clone(), args);
+        let value = result.as_ref().map_or(Atom::Nil, |value| value.clone());
+        if let Some(hook) = self.hook.as_mut() {
+            hook.exit_function(&callee, &value, span);
+        }
+        result
+    }
+
+    //the real streams are used until these are called, an output::Buffer
+    //captures instead
+    pub fn set_stdout(&mut self, out: impl Write + 'static) {
+        self.stdout = Box::new(out);
+    }
+
+    pub fn set_stderr(&mut self, err: impl Write + 'static) {
+        self.stderr.replace(err);
+    }
+
+    pub fn stdout(&mut self) -> &mut dyn Write {
+        &mut self.stdout
+    }
+
+    pub fn stderr(&mut self) -> &mut dyn Write {
+        &mut self.stderr
+    }
+
+    //logs every statement, expression and call to stderr, see
+    //trace::Tracer
+    pub fn trace(&mut self) {
+        self.set_hook(Tracer::new(self.stderr.clone()));
+    }
+
+    //one level deeper, or a runtime error past max_depth. Each level is
+    //also a step of the sandbox
+    fn enter(&mut self) -> bool {
+        if self.depth >= self.max_depth {
+            self.fail(LanguageError::RuntimeError(format!("Nesting deeper than {} levels", self.max_depth)));
+            return false;
+        }
+        if let Err(err) = self.sandbox.step() {
+            self.fail(err);
+            return false;
+        }
+
+        self.depth += 1;
+        true
+    }
+
+    //keeps the first error, anything after it is only its fallout
+    fn fail(&mut self, err: LanguageError) -> Atom {
+        self.error.get_or_insert(err);
+        Atom::Nil
+    }
+
+    //counts a newly made string against the sandbox's memory limit
+    fn allocated(&mut self, value: Atom) -> Atom {
+        let bytes = match &value {
+            Atom::String(s) => s.len(),
+            _ => return value
+        };
+        match self.sandbox.charge(bytes) {
+            Ok(()) => value,
+            Err(err) => self.fail(err)
+        }
+    }
+
+    //a call made by the host rather than by Lox code, the hook sees it
+    //without a position
+    pub fn call(&mut self, callee: Atom, args: Vec<Atom>) -> Result<Atom, LanguageError> {
+        self.call_at(Span::default(), callee, args)
+    }
+
+    fn invoke(&mut self, callee: Atom, args: Vec<Atom>) -> Result<Atom, LanguageError> {
+        match callee {
+            Atom::Function(fun) => {
+                let decl = fun.decl();
+                if args.len() != decl.params.len() {
+                    return Err(LanguageError::RuntimeError(format!("Expected {} arguments but got {}", decl.params.len(), args.len())));
+                }
+
+                let env = Rc::new(RefCell::new(Environment::new(Some(fun.closure.clone()))));
+                for (param, arg) in decl.params.iter().zip(args) {
+                    env.borrow_mut().set(param.clone(), arg);
+                }
+
+                self.sandbox.enter_call()?;
+                let caller = std::mem::replace(&mut self.ast, fun.ast.clone());
+                self.execute_block(&decl.body, env);
+                self.ast = caller;
+                self.sandbox.leave_call();
+                Ok(self.returning.take().unwrap_or(Atom::Nil))
+            },
+            Atom::Native(native) => native.call(args).map(|value| self.allocated(value)),
+            other => Err(LanguageError::TypeError(format!("{} is not callable", other.type_name())))
+        }
+    }
+
+    //makes a Rust function callable from Lox as a global. Arguments and the
+    //result are converted through FromAtom and IntoAtom
+    pub fn define_native<Params>(&mut self, name: &str, fun: impl IntoNative<Params>) -> Result<(), LanguageError> {
+        let name = Symbol::intern(name);
+        let native = fun.into_native(name.clone())?;
+        self.globals.borrow_mut().set(name, Atom::Native(Rc::new(native)));
+        Ok(())
+    }
+
+    fn function(&mut self, decl: FunctionId) -> Atom {
+        if let Err(err) = self.sandbox.charge(std::mem::size_of::<Function>()) {
+            return self.fail(err);
+        }
+        Atom::Function(Rc::new(Function { ast: self.ast.clone(), decl, closure: self.env.clone() }))
+    }
+
+    //locals were checked by the resolver, a global may never have been
+    //defined
+    fn lookup(&mut self, expr: ExprId, name: &Symbol) -> Atom {
+        let global = match self.ast.local_depth(expr) {
+            Some(depth) => return self.env.borrow().get_at(depth, name),
+            None => self.globals.borrow().lookup(name)
+        };
+
+        match global {
+            Some(value) => value,
+            None => self.fail(LanguageError::RuntimeError(format!("Undefined variable {}", name)))
+        }
     }
 
 }
 
 impl StmtVisitor for Interpreter {
-    fn visit_stmt(&mut self, stmt: &Stmt) {
-        match stmt {
-            Stmt::Print(expr) => println!("{:?}", self.evaluate(*expr.clone())),
-            Stmt::Expr(expr) => {self.evaluate(*expr.clone());},
-            Stmt::Var(name, expr) => {
-                let rval: Atom = self.evaluate(*expr.clone());
-                self.env.set(*name.clone(), rval)
+    fn visit_stmt(&mut self, stmt: StmtId) {
+        let ast = self.ast.clone();
+        match ast.stmt(stmt) {
+            Stmt::Block(stmts) => {
+                let env = Rc::new(RefCell::new(Environment::new(Some(self.env.clone()))));
+                self.execute_block(stmts, env);
+            },
+            Stmt::Print(expr) => {
+                let value = self.evaluate(*expr);
+                if self.error.is_none() {
+                    if let Err(err) = writeln!(self.stdout, "{}", value) {
+                        self.fail(LanguageError::IoError(err.to_string()));
+                    }
+                }
+            },
+            Stmt::Expr(expr) => {self.evaluate(*expr);},
+            Stmt::Function(decl, _) => {
+                if let Some(name) = &ast.function(*decl).name {
+                    let fun = self.function(*decl);
+                    self.env.borrow_mut().set(name.clone(), fun);
+                }
+            },
+            Stmt::Return(expr) => {
+                let rval: Atom = self.evaluate(*expr);
+                self.returning = Some(rval);
+            },
+            Stmt::Var(name, expr, _) => {
+                let rval: Atom = self.evaluate(*expr);
+                self.env.borrow_mut().set(name.clone(), rval)
             }
         }
     }
@@ -100,37 +601,127 @@ impl StmtVisitor for Interpreter {
 
 //dynamic heap allocated Any type
 impl ExprVisitor<Atom> for Interpreter {
-    fn visit_expr(&mut self, exp: &Expr) -> Atom {
-        match exp {
+    fn visit_expr(&mut self, exp: ExprId) -> Atom {
+        let ast = self.ast.clone();
+        match ast.expr(exp) {
             Expr::Literal(atom) => atom.clone(),
-            Expr::Grouping(expr) => self.evaluate(*expr.clone()),
+            Expr::Assign(name, op, value) => {
+                let current = if op.token_type == TokenType::EQUAL { Atom::Nil } else { self.lookup(exp, name) };
+                let rval = self.evaluate(*value);
+                let res = match &op.token_type {
+                    TokenType::PLUSEQUAL => Atom::add(current, rval),
+                    TokenType::MINUSEQUAL => Atom::sub(current, rval),
+                    TokenType::STAREQUAL => Atom::mult(current, rval),
+                    TokenType::SLASHEQUAL => Atom::divide(current, rval),
+                    TokenType::PERCENTEQUAL => Atom::modulo(current, rval),
+                    _ => Ok(rval)
+                }.map(|rval| if op.token_type == TokenType::EQUAL { rval } else { self.allocated(rval) })
+                 .and_then(|rval| match ast.local_depth(exp) {
+                    Some(depth) => self.env.borrow_mut().assign_at(depth, name, rval),
+                    None => self.globals.borrow_mut().assign(name, rval)
+                });
+
+                match res {
+                   Ok(atom) => atom,
+                   Err(err) => self.fail(err)
+                }
+            },
+            Expr::Get(object, name) => match self.evaluate(*object) {
+                _ if self.error.is_some() => Atom::Nil,
+                Atom::Object(object) => object.get(name).unwrap_or_else(|err| self.fail(err)),
+                other => self.fail(LanguageError::TypeError(format!("{} has no properties", other.type_name())))
+            },
+            Expr::Set(object, name, op, value) => {
+                let object = match self.evaluate(*object) {
+                    _ if self.error.is_some() => return Atom::Nil,
+                    Atom::Object(object) => object,
+                    other => return self.fail(LanguageError::TypeError(format!("{} has no properties", other.type_name())))
+                };
+                let current = if op.token_type == TokenType::EQUAL { Ok(Atom::Nil) } else { object.get(name) };
+                let rval = self.evaluate(*value);
+                if self.error.is_some() {
+                    return Atom::Nil;
+                }
+
+                let res = current.and_then(|current| match &op.token_type {
+                    TokenType::PLUSEQUAL => Atom::add(current, rval).map(|rval| self.allocated(rval)),
+                    TokenType::MINUSEQUAL => Atom::sub(current, rval),
+                    TokenType::STAREQUAL => Atom::mult(current, rval),
+                    TokenType::SLASHEQUAL => Atom::divide(current, rval),
+                    TokenType::PERCENTEQUAL => Atom::modulo(current, rval),
+                    _ => Ok(rval)
+                }).and_then(|rval| object.set(name, rval.clone()).map(|_| rval));
+
+                match res {
+                   Ok(atom) => atom,
+                   Err(err) => self.fail(err)
+                }
+            },
+            Expr::Grouping(expr) => self.evaluate(*expr),
+            Expr::Conditional(cond, then, otherwise) => {
+                if self.evaluate(*cond).is_truthy() {
+                    self.evaluate(*then)
+                }else{
+                    self.evaluate(*otherwise)
+                }
+            },
             Expr::Binary(lhs, op, rhs) => {
+                let (lhs, rhs) = (*lhs, *rhs);
                 let res = match &op.token_type {
-                    TokenType::PLUS => Atom::add(self.evaluate(*lhs.clone()), self.evaluate(*rhs.clone())),
-                    TokenType::MINUS => Atom::sub(self.evaluate(*lhs.clone()), self.evaluate(*rhs.clone())),
-                    TokenType::STAR => Atom::mult(self.evaluate(*lhs.clone()), self.evaluate(*rhs.clone())),
-                    TokenType::SLASH => Atom::divide(self.evaluate(*lhs.clone()), self.evaluate(*rhs.clone())),
-                    TokenType::BANGEQUAL => Ok(Atom::Bool(self.evaluate(*lhs.clone()) != self.evaluate(*rhs.clone()))),
-                    TokenType::EQUALEQUAL => Ok(Atom::Bool(self.evaluate(*lhs.clone()) == self.evaluate(*rhs.clone()))),
+                    TokenType::COMMA => {
+                        self.evaluate(lhs);
+                        Ok(self.evaluate(rhs))
+                    },
+                    TokenType::PLUS => Atom::add(self.evaluate(lhs), self.evaluate(rhs)),
+                    TokenType::MINUS => Atom::sub(self.evaluate(lhs), self.evaluate(rhs)),
+                    TokenType::STAR => Atom::mult(self.evaluate(lhs), self.evaluate(rhs)),
+                    TokenType::SLASH => Atom::divide(self.evaluate(lhs), self.evaluate(rhs)),
+                    TokenType::PERCENT => Atom::modulo(self.evaluate(lhs), self.evaluate(rhs)),
+                    TokenType::TILDESLASH => Atom::int_divide(self.evaluate(lhs), self.evaluate(rhs)),
+                    TokenType::STARSTAR => Atom::power(self.evaluate(lhs), self.evaluate(rhs)),
+                    TokenType::BANGEQUAL => Ok(Atom::Bool(self.evaluate(lhs) != self.evaluate(rhs))),
+                    TokenType::EQUALEQUAL => Ok(Atom::Bool(self.evaluate(lhs) == self.evaluate(rhs))),
                     TokenType::LESSEQUAL | 
                     TokenType::GREATEREQUAL |
                     TokenType::GREATER  |
-                    TokenType::LESS => Atom::comp(&op.token_type, self.evaluate(*lhs.clone()), self.evaluate(*rhs.clone())),
-                    _ => todo!()
+                    TokenType::LESS => Atom::comp(&op.token_type, self.evaluate(lhs), self.evaluate(rhs)),
+                    _ => Err(LanguageError::RuntimeError(format!("Unknown binary operator {}", op.lexeme)))
                 }; 
 
+                match res {
+                   Ok(atom) if op.token_type == TokenType::PLUS => self.allocated(atom),
+                   Ok(atom) => atom,
+                   Err(err) => self.fail(err)
+                }
+            },
+            Expr::Var(var) => self.lookup(exp, var),
+            Expr::Lambda(decl) => self.function(*decl),
+            Expr::Unary(op, rhs) => {
+                let rhs = self.evaluate(*rhs);
+                let res = match &op.token_type {
+                    TokenType::BANG => Ok(Atom::Bool(!rhs.is_truthy())),
+                    TokenType::MINUS => Atom::negate(rhs),
+                    _ => Err(LanguageError::RuntimeError(format!("Unknown unary operator {}", op.lexeme)))
+                };
+
                 match res {
                    Ok(atom) => atom,
-                   Err(err) => {
-                       self.error = Some(err);
-                       Atom::Nil 
-                   }
+                   Err(err) => self.fail(err)
                 }
             },
-            Expr::Var(var) => self.env.get(*var.clone()),
-            _ => todo!()
+            Expr::Call(callee, _, args) => {
+                let callee = self.evaluate(*callee);
+                let args: Vec<Atom> = args.iter().map(|arg| self.evaluate(*arg)).collect();
+                if self.error.is_some() {
+                    return Atom::Nil;
+                }
+
+                let span = self.ast.expr_span(exp);
+                match self.call_at(span, callee, args) {
+                   Ok(atom) => atom,
+                   Err(err) => self.fail(err)
+                }
+            }
         }
     }
 }
-
-
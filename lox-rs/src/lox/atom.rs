@@ -1,13 +1,21 @@
+use super::ast::Function;
 use super::errors::LanguageError;
+use super::host::Object;
+use super::native::Native;
 use super::scanner::{Token, TokenType};
 use std::fmt;
 use std::convert::From;
+use std::rc::Rc;
 
 #[derive(Debug, Clone)]
 pub enum Atom {
     String(String),
     Number(f64),
     Bool(bool),
+    Function(Rc<Function>),
+    Native(Rc<Native>),
+    //a value owned by the host, see host::HostObject
+    Object(Object),
     Nil
 }
 
@@ -17,6 +25,10 @@ impl PartialEq for Atom {
         match (self, rhs) {
             (Atom::String(lhs), Atom::String(rhs)) => lhs == rhs,
             (Atom::Number(lhs), Atom::Number(rhs)) => lhs == rhs,
+            (Atom::Bool(lhs), Atom::Bool(rhs)) => lhs == rhs,
+            (Atom::Function(lhs), Atom::Function(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Atom::Native(lhs), Atom::Native(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Atom::Object(lhs), Atom::Object(rhs)) => lhs.ptr_eq(rhs),
             (Atom::Nil, Atom::Nil) => true,
             _ => false
         }
@@ -36,18 +48,42 @@ impl From<Token> for Atom {
     }
 }
 
+//the value as print shows it
 impl fmt::Display for Atom {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Atom::String(_) => write!(f, "String"),
-            Atom::Number(_) => write!(f, "Number"),
-            Atom::Bool(_) => write!(f, "Bool"),
-            Atom::Nil => write!(f, "Nil")
+            Atom::String(s) => write!(f, "{}", s),
+            //f64 already leaves off the .0 of whole numbers
+            Atom::Number(n) if n.is_infinite() => write!(f, "{}Infinity", if *n < 0.0 { "-" } else { "" }),
+            Atom::Number(n) => write!(f, "{}", n),
+            Atom::Bool(b) => write!(f, "{}", b),
+            Atom::Function(fun) => write!(f, "{:?}", fun),
+            Atom::Native(native) => write!(f, "{:?}", native),
+            Atom::Object(object) => write!(f, "{:?}", object),
+            Atom::Nil => write!(f, "nil")
         }
     }
 }
 
+#[allow(clippy::should_implement_trait)]
 impl Atom {
+    //what typeof() returns and errors call the value
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Atom::String(_) => "String",
+            Atom::Number(_) => "Number",
+            Atom::Bool(_) => "Bool",
+            Atom::Function(_) | Atom::Native(_) => "Function",
+            Atom::Object(object) => object.type_name(),
+            Atom::Nil => "Nil"
+        }
+    }
+
+    //nil and false are falsey, everything else is truthy
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Atom::Nil | Atom::Bool(false))
+    }
+
     pub fn add(lhs: Self, rhs: Self) -> Result<Self, LanguageError> {
         match (lhs, rhs) {
             (Atom::Number(l) , Atom::Number(r)) => Ok(Atom::Number(l + r)),
@@ -60,17 +96,25 @@ impl Atom {
             (Atom::String(l) , Atom::Bool(r)) => Ok(Atom::String(format!("{}{}", l,r))),
             (Atom::Nil , Atom::String(r)) => Ok(Atom::String(format!("nil{}", r))),
             (Atom::String(l), Atom::Nil) => Ok(Atom::String(format!("{}nil", l))),
-            (l,r) => Err(LanguageError::TypeError(format!("Addition not supported for {} and {}",l,r))) 
+            (l,r) => Err(LanguageError::TypeError(format!("Addition not supported for {} and {}", l.type_name(), r.type_name()))) 
 
         }
     }
 
+    pub fn negate(rhs: Self) -> Result<Self, LanguageError> {
+        match rhs {
+            Atom::Number(r) => Ok(Atom::Number(-r)),
+            Atom::Bool(r) => Ok(Atom::Number(if r {-1_f64} else {0_f64})),
+            r => Err(LanguageError::TypeError(format!("Negation not supported for {}", r.type_name())))
+        }
+    }
+
     pub fn sub(lhs: Self, rhs: Self) -> Result<Self, LanguageError> {
         match (lhs, rhs) {
             (Atom::Number(l) , Atom::Number(r)) => Ok(Atom::Number(l - r)),
             (Atom::Number(l) , Atom::Bool(r)) => Ok(Atom::Number(l - if r {1_f64} else {0_f64})),
             (Atom::Bool(l) , Atom::Number(r)) => Ok(Atom::Number(if l {1_f64} else {0_f64} - r)),
-            (l,r) => Err(LanguageError::TypeError(format!("Subtraction not supported for {} and {}",l,r))) 
+            (l,r) => Err(LanguageError::TypeError(format!("Subtraction not supported for {} and {}", l.type_name(), r.type_name()))) 
         }
 
     }
@@ -80,7 +124,7 @@ impl Atom {
             (Atom::Number(l) , Atom::Number(r)) => Ok(Atom::Number(l * r)),
             (Atom::Number(l) , Atom::Bool(r)) => Ok(Atom::Number(l * if r {1_f64} else {0_f64})),
             (Atom::Bool(l) , Atom::Number(r)) => Ok(Atom::Number(if l {1_f64} else {0_f64} * r)),
-            (l,r) => Err(LanguageError::TypeError(format!("Multiplication not supported for {} and {}",l,r))) 
+            (l,r) => Err(LanguageError::TypeError(format!("Multiplication not supported for {} and {}", l.type_name(), r.type_name()))) 
         }
 
     }
@@ -90,7 +134,37 @@ impl Atom {
             (Atom::Number(l) , Atom::Number(r)) => Ok(Atom::Number(l / r)),
             (Atom::Number(l) , Atom::Bool(r)) => Ok(Atom::Number(l / if r {1_f64} else {0_f64})),
             (Atom::Bool(l) , Atom::Number(r)) => Ok(Atom::Number(if l {1_f64} else {0_f64} / r)),
-            (l,r) => Err(LanguageError::TypeError(format!("Division not supported for {} and {}",l,r))) 
+            (l,r) => Err(LanguageError::TypeError(format!("Division not supported for {} and {}", l.type_name(), r.type_name()))) 
+        }
+
+    }
+
+    pub fn modulo(lhs: Self, rhs: Self) -> Result<Self, LanguageError> {
+        match (lhs, rhs) {
+            (Atom::Number(l) , Atom::Number(r)) => Ok(Atom::Number(l % r)),
+            (Atom::Number(l) , Atom::Bool(r)) => Ok(Atom::Number(l % if r {1_f64} else {0_f64})),
+            (Atom::Bool(l) , Atom::Number(r)) => Ok(Atom::Number(if l {1_f64} else {0_f64} % r)),
+            (l,r) => Err(LanguageError::TypeError(format!("Modulo not supported for {} and {}", l.type_name(), r.type_name()))) 
+        }
+
+    }
+
+    pub fn int_divide(lhs: Self, rhs: Self) -> Result<Self, LanguageError> {
+        match (lhs, rhs) {
+            (Atom::Number(l) , Atom::Number(r)) => Ok(Atom::Number((l / r).floor())),
+            (Atom::Number(l) , Atom::Bool(r)) => Ok(Atom::Number((l / if r {1_f64} else {0_f64}).floor())),
+            (Atom::Bool(l) , Atom::Number(r)) => Ok(Atom::Number((if l {1_f64} else {0_f64} / r).floor())),
+            (l,r) => Err(LanguageError::TypeError(format!("Integer division not supported for {} and {}", l.type_name(), r.type_name()))) 
+        }
+
+    }
+
+    pub fn power(lhs: Self, rhs: Self) -> Result<Self, LanguageError> {
+        match (lhs, rhs) {
+            (Atom::Number(l) , Atom::Number(r)) => Ok(Atom::Number(l.powf(r))),
+            (Atom::Number(l) , Atom::Bool(r)) => Ok(Atom::Number(l.powf(if r {1_f64} else {0_f64}))),
+            (Atom::Bool(l) , Atom::Number(r)) => Ok(Atom::Number(if l {1_f64} else {0_f64}.powf(r))),
+            (l,r) => Err(LanguageError::TypeError(format!("Exponentiation not supported for {} and {}", l.type_name(), r.type_name()))) 
         }
 
     }
@@ -105,7 +179,7 @@ impl Atom {
                 _ => unreachable!()
             }
         }else{
-            Err(LanguageError::TypeError(format!("{:?} not supported between {} and {}", t, lhs, rhs)))
+            Err(LanguageError::TypeError(format!("{:?} not supported between {} and {}", t, lhs.type_name(), rhs.type_name())))
         }
     }
 
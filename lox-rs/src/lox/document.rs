@@ -0,0 +1,307 @@
+use super::ast::{Ast, Span, StmtId};
+use super::errors::LanguageError;
+use super::parser::Parser;
+use super::scanner::{Scanner, Token};
+
+use std::ops::Range;
+use std::rc::Rc;
+
+//a top level declaration along with the tokens it was parsed from, the
+//declarations parsed together share one Ast. Spans in the Ast are where
+//the declaration was when it was parsed, line_shift is how many lines it
+//has moved down since
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    pub ast: Rc<Ast>,
+    pub stmt: StmtId,
+    pub tokens: Range<usize>,
+    pub errors: Vec<LanguageError>,
+    pub line_shift: i32
+}
+
+impl Declaration {
+    //where the declaration is now
+    pub fn span(&self) -> Span {
+        let span = self.ast.stmt_span(self.stmt);
+        Span { line: span.line + self.line_shift, end_line: span.end_line + self.line_shift }
+    }
+}
+
+//a scanned and parsed source file that can be edited in place, only the
+//tokens and declarations around an edit are recomputed. Tokens and
+//declarations the edit doesn't reach keep their identity (same Rc), even
+//when the edit moves them to another line
+pub struct Document {
+    source: String,
+    //each keeps the line it was scanned on, see line()
+    tokens: Vec<Rc<Token>>,
+    //how many lines tokens have moved since they were scanned, as the
+    //first token of each run that moved the same, in order
+    shifts: Vec<(usize, i32)>,
+    decls: Vec<Declaration>,
+    scan_errors: Vec<LanguageError>
+}
+
+impl Document {
+    pub fn new(source: String) -> Document {
+        let mut scanner = Scanner::lossless(source.clone());
+        let _ = scanner.scan_tokens();
+
+        let mut doc = Document {
+            source,
+            tokens: scanner.tokens().iter().cloned().map(Rc::new).collect(),
+            shifts: Vec::new(),
+            decls: Vec::new(),
+            scan_errors: scanner.errors().clone()
+        };
+        doc.decls = doc.parse_window(0..doc.eof());
+        doc
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    //the line on a token is the one it was scanned on, line() has the one
+    //it is on now
+    pub fn tokens(&self) -> &[Rc<Token>] {
+        &self.tokens
+    }
+
+    pub fn line(&self, token: usize) -> i32 {
+        self.tokens[token].line + self.shift(token)
+    }
+
+    pub fn declarations(&self) -> &[Declaration] {
+        &self.decls
+    }
+
+    pub fn errors(&self) -> Vec<&LanguageError> {
+        self.scan_errors
+            .iter()
+            .chain(self.decls.iter().flat_map(|decl| decl.errors.iter()))
+            .collect()
+    }
+
+    //replaces the given byte range of the source with text. The range has
+    //to be within the source and on character boundaries
+    pub fn apply_edit(&mut self, range: Range<usize>, text: &str) -> Result<(), String> {
+        if range.start > range.end || !self.source.is_char_boundary(range.start) || !self.source.is_char_boundary(range.end) {
+            return Err(format!("Invalid edit range {:?} for a source of {} bytes", range, self.source.len()));
+        }
+
+        let starts = self.token_starts();
+        let eof = self.eof();
+
+        //every token whose text (trivia included) touches the edit, with
+        //one more token on each side so trivia gets redistributed
+        let first = (0..=eof).find(|&i| starts[i + 1] >= range.start).unwrap_or(eof);
+        let last = (0..=eof).rev().find(|&i| starts[i] <= range.end).unwrap_or(0);
+        let lo = first.saturating_sub(1);
+        let mut hi = (last + 1).min(eof);
+
+        let delta = text.len() as isize - range.len() as isize;
+        let line_delta = newlines(text) - newlines(&self.source[range.clone()]);
+        self.source.replace_range(range, text);
+
+        //rescan, growing the region to the end of the file when the new
+        //tokens don't line up with the old ones
+        let (tokens, errors) = loop {
+            let end = (starts[hi + 1] as isize + delta) as usize;
+            match self.scan_region(starts[lo]..end, &self.tokens[hi], hi == eof) {
+                Some(scanned) => break scanned,
+                None => hi = eof
+            }
+        };
+
+        if hi == eof {
+            self.scan_errors = errors;
+        }
+
+        //tokens at the edges of the region that came out the same are
+        //kept, the ones after the edit have moved by line_delta
+        let same = |i: usize, new: &Token, moved: i32| {
+            let old = &self.tokens[i];
+            old.line + self.shift(i) + moved == new.line && Token { line: new.line, ..(**old).clone() } == *new
+        };
+        let prefix = (lo..=hi)
+                        .zip(tokens.iter())
+                        .take_while(|(i, new)| same(*i, new, 0))
+                        .count();
+        let suffix = (lo + prefix..=hi)
+                        .rev()
+                        .zip(tokens[prefix..].iter().rev())
+                        .take_while(|(i, new)| same(*i, new, line_delta))
+                        .count();
+        let old_len = hi + 1 - lo;
+        if prefix + suffix == old_len && old_len == tokens.len() {
+            return Ok(());
+        }
+
+        let start = lo + prefix;
+        let removed = old_len - prefix - suffix;
+        let base = self.shift(start);
+        let changed: Vec<Rc<Token>> = tokens[prefix..tokens.len() - suffix]
+                                        .iter()
+                                        .map(|token| Rc::new(Token { line: token.line - base, ..token.clone() }))
+                                        .collect();
+        let shift = changed.len() as isize - removed as isize;
+        self.shifted(start, removed, changed.len(), line_delta);
+        self.tokens.splice(start..start + removed, changed);
+
+        self.reparse(start, start + removed.saturating_sub(1), shift, line_delta);
+        Ok(())
+    }
+
+    //how many lines the token has moved since it was scanned
+    fn shift(&self, token: usize) -> i32 {
+        match self.shifts.partition_point(|(first, _)| *first <= token) {
+            0 => 0,
+            n => self.shifts[n - 1].1
+        }
+    }
+
+    //the removed tokens at start were replaced by added new ones, scanned
+    //in place, and everything after moved by line_delta
+    fn shifted(&mut self, start: usize, removed: usize, added: usize, line_delta: i32) {
+        let after = self.shift(start + removed) + line_delta;
+        let mut shifts: Vec<(usize, i32)> = self.shifts.iter().copied().filter(|(first, _)| *first <= start).collect();
+        shifts.push((start + added, after));
+        shifts.extend(self.shifts
+                        .iter()
+                        .filter(|(first, _)| *first > start + removed)
+                        .map(|(first, lines)| (first + added - removed, lines + line_delta)));
+
+        //runs that moved the same as the one before are merged
+        self.shifts.clear();
+        for (first, lines) in shifts {
+            if lines != self.shifts.last().map_or(0, |(_, last)| *last) {
+                self.shifts.push((first, lines));
+            }
+        }
+    }
+
+    fn reparse(&mut self, lo: usize, hi: usize, shift: isize, line_delta: i32) {
+        let shifted = |i: usize| (i as isize + shift) as usize;
+        let eof = self.eof();
+
+        //a declaration with errors stopped at whatever token came next, so
+        //it has to be reparsed along with the declaration after it
+        let mut first = self.decls
+                            .iter()
+                            .position(|decl| decl.tokens.end > lo)
+                            .unwrap_or(self.decls.len());
+        while first > 0 && !self.decls[first - 1].errors.is_empty() {
+            first -= 1;
+        }
+        let window_start = self.decls.get(first).map_or(
+                                self.decls.last().map_or(0, |decl| decl.tokens.end),
+                                |decl| decl.tokens.start);
+        let window_end = match self.decls.iter().rev().find(|decl| decl.tokens.start <= hi) {
+            Some(decl) if decl.tokens.end > hi => shifted(decl.tokens.end),
+            _ => eof
+        };
+
+        //the window is reparsed on its own, if that doesn't come out clean
+        //the edit may reach further and the rest of the file is reparsed
+        let mut window = self.parse_window(window_start..window_end);
+        let mut after: Vec<Declaration> = Vec::new();
+        if window_end != eof {
+            let clean = window.iter().all(|decl| decl.errors.is_empty())
+                        && window.last().map_or(window_start, |decl| decl.tokens.end) == window_end;
+            if clean {
+                after = self.decls
+                            .iter()
+                            .filter(|decl| decl.tokens.start > hi)
+                            .cloned()
+                            .collect();
+            }else{
+                window = self.parse_window(window_start..eof);
+            }
+        }
+
+        self.decls.retain(|decl| decl.tokens.end <= window_start);
+        self.decls.extend(window);
+
+        for decl in after {
+            let tokens = shifted(decl.tokens.start)..shifted(decl.tokens.end);
+            self.decls.push(Declaration { tokens, line_shift: decl.line_shift + line_delta, ..decl });
+        }
+    }
+
+    //parses tokens[range] as a sequence of declarations
+    fn parse_window(&self, range: Range<usize>) -> Vec<Declaration> {
+        let tokens: Vec<Token> = range.clone()
+                                        .chain([self.eof()])
+                                        .map(|i| Token { line: self.line(i), ..(*self.tokens[i]).clone() })
+                                        .collect();
+
+        let mut ast = Ast::default();
+        let decls = Parser::new(&tokens, &mut ast).declarations();
+        let ast = Rc::new(ast);
+        //the window ends in a stand in for EOF, running into it only means
+        //the input is incomplete when it really is the end of the file
+        let at_eof = range.end == self.eof();
+        decls
+            .into_iter()
+            .map(|(stmt, tokens, errors)| Declaration {
+                ast: ast.clone(),
+                stmt,
+                tokens: tokens.start + range.start..tokens.end + range.start,
+                line_shift: 0,
+                errors: errors.into_iter().map(|err| match err {
+                    LanguageError::IncompleteInput(msg) if !at_eof => LanguageError::ParserError(msg),
+                    err => err
+                }).collect()
+            })
+            .collect()
+    }
+
+    //scans source[region], region has to end where `last` used to end,
+    //unless it runs to the end of the file
+    fn scan_region(&self, region: Range<usize>, last: &Token, to_eof: bool) -> Option<(Vec<Token>, Vec<LanguageError>)> {
+        let base_line = newlines(&self.source[..region.start]);
+        let mut scanner = Scanner::lossless(self.source[region].into());
+        let _ = scanner.scan_tokens();
+
+        let mut tokens: Vec<Token> = scanner.tokens().clone();
+        for token in tokens.iter_mut() {
+            token.line += base_line;
+        }
+
+        if to_eof {
+            return Some((tokens, scanner.errors().clone()));
+        }
+
+        let eof = tokens.pop()?;
+        let lines_up = match tokens.last() {
+            Some(token) => token.lexeme == last.lexeme && token.trailing_trivia == last.trailing_trivia,
+            None => false
+        };
+
+        if scanner.errors().is_empty() && eof.leading_trivia.is_empty() && eof.lexeme.is_empty() && lines_up {
+            Some((tokens, Vec::new()))
+        }else{
+            None
+        }
+    }
+
+    //byte offset each token starts at (trivia included), followed by the
+    //length of the source
+    fn token_starts(&self) -> Vec<usize> {
+        let mut starts = vec![0];
+        for token in self.tokens.iter() {
+            let len = token.leading_trivia.len() + token.lexeme.len() + token.trailing_trivia.len();
+            starts.push(starts.last().unwrap() + len);
+        }
+        starts
+    }
+
+    fn eof(&self) -> usize {
+        self.tokens.len() - 1
+    }
+}
+
+fn newlines(text: &str) -> i32 {
+    text.matches('\n').count() as i32
+}
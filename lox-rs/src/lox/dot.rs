@@ -0,0 +1,253 @@
+use super::ast::{Ast, Expr, ExprId, FunctionId, Stmt, StmtId};
+use super::format;
+use super::scanner::Token;
+use super::symbol::Symbol;
+
+use std::collections::HashMap;
+
+//writes the program as a Graphviz digraph with a node for every statement,
+//expression and operator token, edges are labelled with the field they
+//come from. With scopes set every block and function body is drawn as a
+//box and each resolved variable gets a dashed edge to where it was
+//declared, the Ast has to have been resolved for that
+pub fn program(ast: &Ast, stmts: &[StmtId], scopes: bool) -> String {
+    let mut graph = Graph {
+        ast,
+        out: String::new(),
+        indent: 1,
+        nodes: 0,
+        clusters: 0,
+        scopes: if scopes { Some(Vec::new()) } else { None }
+    };
+
+    graph.out.push_str("digraph ast {\n");
+    graph.line("ordering=out;");
+    graph.line("node [shape=box, fontname=\"monospace\"];");
+    graph.line("edge [fontsize=10];");
+    let root = graph.node("Program", "ellipse");
+    for (i, stmt) in stmts.iter().enumerate() {
+        let child = graph.stmt(*stmt);
+        graph.edge(root, child, &i.to_string());
+    }
+    graph.out.push_str("}\n");
+    graph.out
+}
+
+struct Graph<'a> {
+    ast: &'a Ast,
+    out: String,
+    indent: usize,
+    nodes: usize,
+    clusters: usize,
+    //the node each local was declared by, innermost scope last. None when
+    //scopes aren't drawn, globals are never in here
+    scopes: Option<Vec<HashMap<Symbol, usize>>>
+}
+
+impl Graph<'_> {
+    fn stmt(&mut self, id: StmtId) -> usize {
+        let ast = self.ast;
+        match ast.stmt(id) {
+            Stmt::Block(stmts) => {
+                let node = self.node("Block", "box");
+                self.scope("block", |graph| {
+                    for (i, stmt) in stmts.iter().enumerate() {
+                        let child = graph.stmt(*stmt);
+                        graph.edge(node, child, &i.to_string());
+                    }
+                });
+                node
+            },
+            Stmt::Expr(expr) => self.parent("Expression", &[("expression", *expr)]),
+            Stmt::Function(decl, _) => {
+                let name = ast.function(*decl).name.as_ref().map_or(String::new(), |name| name.to_string());
+                let node = self.node(&format!("Function {}", name), "box");
+                if let Some(name) = &ast.function(*decl).name {
+                    self.declare(name.clone(), node);
+                }
+                self.function(node, *decl);
+                node
+            },
+            Stmt::Print(expr) => self.parent("Print", &[("expression", *expr)]),
+            Stmt::Return(value) => self.parent("Return", &[("value", *value)]),
+            Stmt::Var(name, init, _) => {
+                let node = self.node(&format!("Var {}", name), "box");
+                let child = self.expr(*init);
+                self.edge(node, child, "initializer");
+                self.declare(name.clone(), node);
+                node
+            }
+        }
+    }
+
+    fn expr(&mut self, id: ExprId) -> usize {
+        let ast = self.ast;
+        match ast.expr(id) {
+            Expr::Assign(name, op, value) => {
+                let node = self.node(&format!("Assign {}{}", name, self.depth(id)), "box");
+                self.resolved(id, name, node);
+                self.token(node, op);
+                let child = self.expr(*value);
+                self.edge(node, child, "value");
+                node
+            },
+            Expr::Binary(lhs, op, rhs) => {
+                let node = self.node("Binary", "box");
+                let left = self.expr(*lhs);
+                self.edge(node, left, "left");
+                self.token(node, op);
+                let right = self.expr(*rhs);
+                self.edge(node, right, "right");
+                node
+            },
+            Expr::Call(callee, _, args) => {
+                let node = self.parent("Call", &[("callee", *callee)]);
+                for (i, arg) in args.iter().enumerate() {
+                    let child = self.expr(*arg);
+                    self.edge(node, child, &i.to_string());
+                }
+                node
+            },
+            Expr::Get(object, name) => self.parent(&format!("Get {}", name), &[("object", *object)]),
+            Expr::Set(object, name, op, value) => {
+                let node = self.node(&format!("Set {}", name), "box");
+                let child = self.expr(*object);
+                self.edge(node, child, "object");
+                self.token(node, op);
+                let child = self.expr(*value);
+                self.edge(node, child, "value");
+                node
+            },
+            Expr::Grouping(inner) => self.parent("Grouping", &[("expression", *inner)]),
+            Expr::Lambda(decl) => {
+                let node = self.node("Lambda", "box");
+                self.function(node, *decl);
+                node
+            },
+            Expr::Conditional(cond, then, otherwise) => {
+                self.parent("Conditional", &[("condition", *cond), ("then", *then), ("else", *otherwise)])
+            },
+            Expr::Literal(atom) => self.node(&format!("Literal\n{}", format::literal(atom).unwrap_or_else(|| format!("\"{}\"", atom))), "box"),
+            Expr::Unary(op, rhs) => {
+                let node = self.node("Unary", "box");
+                self.token(node, op);
+                let child = self.expr(*rhs);
+                self.edge(node, child, "right");
+                node
+            },
+            Expr::Var(name) => {
+                let node = self.node(&format!("Var {}{}", name, self.depth(id)), "box");
+                self.resolved(id, name, node);
+                node
+            }
+        }
+    }
+
+    //params and body of a named function or lambda, in the function's scope
+    fn function(&mut self, node: usize, id: FunctionId) {
+        let decl = self.ast.function(id);
+        self.scope("function", |graph| {
+            for param in decl.params.iter() {
+                let child = graph.node(&format!("param {}", param), "ellipse");
+                graph.edge(node, child, "params");
+                graph.declare(param.clone(), child);
+            }
+            for (i, stmt) in decl.body.iter().enumerate() {
+                let child = graph.stmt(*stmt);
+                graph.edge(node, child, &format!("body {}", i));
+            }
+        });
+    }
+
+    //a node whose children are all expressions
+    fn parent(&mut self, label: &str, children: &[(&str, ExprId)]) -> usize {
+        let node = self.node(label, "box");
+        for (field, expr) in children {
+            let child = self.expr(*expr);
+            self.edge(node, child, field);
+        }
+        node
+    }
+
+    fn token(&mut self, parent: usize, token: &Token) {
+        let node = self.node(&token.lexeme, "plaintext");
+        self.edge(parent, node, "operator");
+    }
+
+    fn node(&mut self, label: &str, shape: &str) -> usize {
+        self.nodes += 1;
+        self.line(&format!("n{} [label=\"{}\", shape={}];", self.nodes, escape(label), shape));
+        self.nodes
+    }
+
+    fn edge(&mut self, from: usize, to: usize, label: &str) {
+        self.line(&format!("n{} -> n{} [label=\"{}\"];", from, to, escape(label)));
+    }
+
+    //nodes written inside f are drawn in a box when scopes are on. Nodes
+    //are always written before any edge to them, so a node ends up in the
+    //box it was declared in
+    fn scope(&mut self, label: &str, f: impl FnOnce(&mut Self)) {
+        let Some(scopes) = self.scopes.as_mut() else {
+            return f(self);
+        };
+        scopes.push(HashMap::new());
+
+        self.clusters += 1;
+        self.line(&format!("subgraph cluster_{} {{", self.clusters));
+        self.indent += 1;
+        self.line(&format!("label=\"{}\"; style=dashed; color=gray;", label));
+        f(self);
+        self.indent -= 1;
+        self.line("}");
+
+        if let Some(scopes) = self.scopes.as_mut() {
+            scopes.pop();
+        }
+    }
+
+    fn declare(&mut self, name: Symbol, node: usize) {
+        if let Some(scope) = self.scopes.as_mut().and_then(|scopes| scopes.last_mut()) {
+            scope.insert(name, node);
+        }
+    }
+
+    //the dashed edge from a variable to its declaration
+    fn resolved(&mut self, id: ExprId, name: &Symbol, node: usize) {
+        let declared = match (&self.scopes, self.ast.local_depth(id)) {
+            (Some(scopes), Some(depth)) if depth < scopes.len() => scopes[scopes.len() - 1 - depth].get(name).copied(),
+            _ => None
+        };
+        if let Some(declared) = declared {
+            self.line(&format!("n{} -> n{} [style=dashed, color=blue, constraint=false];", node, declared));
+        }
+    }
+
+    //second line of a variable's label, how far out it was declared
+    fn depth(&self, id: ExprId) -> String {
+        match (&self.scopes, self.ast.local_depth(id)) {
+            (None, _) => String::new(),
+            (Some(_), Some(depth)) => format!("\ndepth {}", depth),
+            (Some(_), None) => String::from("\nglobal")
+        }
+    }
+
+    fn line(&mut self, text: &str) {
+        self.out.push_str(&"    ".repeat(self.indent));
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+}
+
+fn escape(label: &str) -> String {
+    let mut escaped = String::new();
+    for c in label.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
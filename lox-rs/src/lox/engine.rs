@@ -0,0 +1,184 @@
+use super::ast::{Ast, Interpreter, StmtId};
+use super::atom::Atom;
+use super::errors::LanguageError;
+use super::native::IntoNative;
+use super::sandbox::{CancelHandle, Limits};
+use super::parser::Parser;
+use super::resolver;
+use super::scanner::Scanner;
+use super::session;
+use super::trace::Hook;
+use super::symbol::Symbol;
+
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::rc::Rc;
+
+//whatever stopped a piece of code from running. Scanning, parsing and
+//resolving report everything they find, evaluation stops at its first
+//error
+#[derive(Debug, Clone)]
+pub struct Error {
+    pub errors: Vec<LanguageError>
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, err) in self.errors.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", err)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<Vec<LanguageError>> for Error {
+    fn from(errors: Vec<LanguageError>) -> Self {
+        Error { errors }
+    }
+}
+
+impl From<LanguageError> for Error {
+    fn from(err: LanguageError) -> Self {
+        Error { errors: vec![err] }
+    }
+}
+
+//a Lox interpreter for embedding. Globals persist between calls, so code
+//evaluated later sees the functions and variables defined earlier
+#[derive(Default)]
+pub struct Engine {
+    intp: Interpreter
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Engine::default()
+    }
+
+    //runs source and returns the value of its last statement when that is
+    //an expression, nil otherwise
+    pub fn eval(&mut self, source: &str) -> Result<Atom, Error> {
+        let (ast, stmts) = parse(source)?;
+        self.run(ast, &stmts)
+    }
+
+    pub fn run_file(&mut self, path: impl AsRef<Path>) -> Result<Atom, Error> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+                        .map_err(|err| LanguageError::IoError(format!("{}: {}", path.display(), err)))?;
+        self.eval(&source)
+    }
+
+    //writes the globals to path as Lox source, load_session or run_file
+    //in a fresh engine brings them back. See session::save for what can't
+    //be kept
+    pub fn save_session(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        fs::write(path, session::save(&self.intp.globals))
+            .map_err(|err| LanguageError::IoError(format!("{}: {}", path.display(), err)).into())
+    }
+
+    pub fn load_session(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+        self.run_file(path).map(|_| ())
+    }
+
+    //resolves and runs a program that was already parsed or loaded
+    pub fn run(&mut self, mut ast: Ast, stmts: &[StmtId]) -> Result<Atom, Error> {
+        resolver::resolve(&mut ast, stmts)?;
+        let value = self.intp.interpret(&Rc::new(ast), stmts);
+        self.finish(value)
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Atom> {
+        self.intp.globals.borrow().lookup(&Symbol::intern(name))
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Atom) {
+        self.intp.globals.borrow_mut().set(Symbol::intern(name), value);
+    }
+
+    //sends print output somewhere other than the process's stdout, e.g.
+    //an output::Buffer
+    pub fn set_stdout(&mut self, out: impl Write + 'static) {
+        self.intp.set_stdout(out);
+    }
+
+    //where report and trace write
+    pub fn set_stderr(&mut self, err: impl Write + 'static) {
+        self.intp.set_stderr(err);
+    }
+
+    //writes the errors to stderr one per line, the way rlox shows them. A
+    //report that can't be written is dropped
+    pub fn report(&mut self, err: &Error) {
+        for err in err.errors.iter() {
+            let _ = writeln!(self.intp.stderr(), "{:?}", err);
+        }
+    }
+
+    //see trace::Hook
+    pub fn set_hook(&mut self, hook: impl Hook + 'static) {
+        self.intp.set_hook(hook);
+    }
+
+    //logs every statement, expression and call to stderr
+    pub fn trace(&mut self) {
+        self.intp.trace();
+    }
+
+    //limits every later run, see sandbox::Limits
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.intp.sandbox.limits = limits;
+    }
+
+    //for stopping a run from another thread. A cancel that arrives when
+    //nothing is running is dropped when the next run starts
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.intp.sandbox.cancel_handle()
+    }
+
+    //registers a Rust closure or fn as a global Lox function, e.g.
+    //engine.register("shout", |s: String| s.to_uppercase()). Fails when
+    //a Vec parameter isn't the last one
+    pub fn register<Params>(&mut self, name: &str, fun: impl IntoNative<Params>) -> Result<(), Error> {
+        Ok(self.intp.define_native(name, fun)?)
+    }
+
+    //calls a Lox function, usually one fetched with get_global
+    pub fn call(&mut self, function: &Atom, args: Vec<Atom>) -> Result<Atom, Error> {
+        self.intp.sandbox.start();
+        let value = self.intp.call(function.clone(), args)?;
+        self.finish(value)
+    }
+
+    //for settings the engine doesn't wrap, like max_depth
+    pub fn interpreter(&mut self) -> &mut Interpreter {
+        &mut self.intp
+    }
+
+    fn finish(&mut self, value: Atom) -> Result<Atom, Error> {
+        match self.intp.error.take() {
+            Some(err) => Err(err.into()),
+            None => Ok(value)
+        }
+    }
+}
+
+pub fn parse(source: &str) -> Result<(Ast, Vec<StmtId>), Error> {
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens().map_err(|errs| errs.clone())?.clone();
+
+    let mut ast = Ast::default();
+    let mut parser = Parser::new(&tokens, &mut ast);
+    match parser.parse() {
+        Ok(stmts) => Ok((ast, stmts)),
+        Err(_) => Err(parser.errors().clone().into())
+    }
+}
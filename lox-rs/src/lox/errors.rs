@@ -1,7 +1,54 @@
-#[derive(Debug)]
+use std::fmt;
+
+#[derive(Debug, Clone)]
 pub enum LanguageError {
     SyntaxError(String),
     ParserError(String),
-    TypeError(String)
+    //the source ended before a string, comment, bracket or statement was
+    //finished
+    IncompleteInput(String),
+    ResolveError(String),
+    TypeError(String),
+    RuntimeError(String),
+    //a script couldn't be read
+    IoError(String),
+    //the script ran past one of its sandbox limits or was cancelled
+    LimitError(String)
+
+}
+
+impl LanguageError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LanguageError::SyntaxError(_) => "SyntaxError",
+            LanguageError::ParserError(_) => "ParserError",
+            LanguageError::IncompleteInput(_) => "IncompleteInput",
+            LanguageError::ResolveError(_) => "ResolveError",
+            LanguageError::TypeError(_) => "TypeError",
+            LanguageError::RuntimeError(_) => "RuntimeError",
+            LanguageError::IoError(_) => "IoError",
+            LanguageError::LimitError(_) => "LimitError"
+        }
+    }
 
+    pub fn message(&self) -> &str {
+        match self {
+            LanguageError::SyntaxError(msg) |
+            LanguageError::ParserError(msg) |
+            LanguageError::IncompleteInput(msg) |
+            LanguageError::ResolveError(msg) |
+            LanguageError::TypeError(msg) |
+            LanguageError::RuntimeError(msg) |
+            LanguageError::IoError(msg) |
+            LanguageError::LimitError(msg) => msg
+        }
+    }
 }
+
+impl fmt::Display for LanguageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind(), self.message())
+    }
+}
+
+impl std::error::Error for LanguageError {}
@@ -0,0 +1,174 @@
+use super::ast::{Ast, Expr, ExprId, FunctionDecl, FunctionId, Stmt, StmtId};
+use super::atom::Atom;
+use super::scanner::Token;
+use super::symbol::Symbol;
+
+//a rewriting pass over the tree with one method per kind of node, each
+//returns the id of the node to use in place of the one it was given.
+//Nodes are never changed in place: the defaults fold the children and
+//only add a new node to the Ast when one of them came back different, so
+//an untouched subtree keeps its ids. A replacement node keeps the span
+//and resolution data of the node it replaces
+pub trait Fold {
+    fn fold_stmt(&mut self, ast: &mut Ast, id: StmtId) -> StmtId {
+        walk_stmt(self, ast, id)
+    }
+
+    fn fold_expr(&mut self, ast: &mut Ast, id: ExprId) -> ExprId {
+        walk_expr(self, ast, id)
+    }
+
+    //named functions and lambdas both end up here
+    fn fold_function(&mut self, ast: &mut Ast, id: FunctionId) -> FunctionId {
+        walk_function(self, ast, id)
+    }
+
+    fn fold_block_stmt(&mut self, ast: &mut Ast, id: StmtId, stmts: Vec<StmtId>) -> StmtId {
+        let folded = fold_stmts(self, ast, &stmts);
+        replace_stmt(ast, id, folded != stmts, Stmt::Block(folded))
+    }
+
+    fn fold_expr_stmt(&mut self, ast: &mut Ast, id: StmtId, expr: ExprId) -> StmtId {
+        let folded = self.fold_expr(ast, expr);
+        replace_stmt(ast, id, folded != expr, Stmt::Expr(folded))
+    }
+
+    fn fold_function_stmt(&mut self, ast: &mut Ast, id: StmtId, decl: FunctionId, doc: Option<String>) -> StmtId {
+        let folded = self.fold_function(ast, decl);
+        replace_stmt(ast, id, folded != decl, Stmt::Function(folded, doc))
+    }
+
+    fn fold_print_stmt(&mut self, ast: &mut Ast, id: StmtId, expr: ExprId) -> StmtId {
+        let folded = self.fold_expr(ast, expr);
+        replace_stmt(ast, id, folded != expr, Stmt::Print(folded))
+    }
+
+    fn fold_return_stmt(&mut self, ast: &mut Ast, id: StmtId, expr: ExprId) -> StmtId {
+        let folded = self.fold_expr(ast, expr);
+        replace_stmt(ast, id, folded != expr, Stmt::Return(folded))
+    }
+
+    fn fold_var_stmt(&mut self, ast: &mut Ast, id: StmtId, name: Symbol, init: ExprId, doc: Option<String>) -> StmtId {
+        let folded = self.fold_expr(ast, init);
+        replace_stmt(ast, id, folded != init, Stmt::Var(name, folded, doc))
+    }
+
+    fn fold_assign(&mut self, ast: &mut Ast, id: ExprId, name: Symbol, op: Token, value: ExprId) -> ExprId {
+        let folded = self.fold_expr(ast, value);
+        replace_expr(ast, id, folded != value, Expr::Assign(name, op, folded))
+    }
+
+    fn fold_binary(&mut self, ast: &mut Ast, id: ExprId, lhs: ExprId, op: Token, rhs: ExprId) -> ExprId {
+        let (new_lhs, new_rhs) = (self.fold_expr(ast, lhs), self.fold_expr(ast, rhs));
+        replace_expr(ast, id, (new_lhs, new_rhs) != (lhs, rhs), Expr::Binary(new_lhs, op, new_rhs))
+    }
+
+    fn fold_call(&mut self, ast: &mut Ast, id: ExprId, callee: ExprId, paren: Token, args: Vec<ExprId>) -> ExprId {
+        let new_callee = self.fold_expr(ast, callee);
+        let new_args: Vec<ExprId> = args.iter().map(|arg| self.fold_expr(ast, *arg)).collect();
+        let changed = new_callee != callee || new_args != args;
+        replace_expr(ast, id, changed, Expr::Call(new_callee, paren, new_args))
+    }
+
+    fn fold_get(&mut self, ast: &mut Ast, id: ExprId, object: ExprId, name: Symbol) -> ExprId {
+        let folded = self.fold_expr(ast, object);
+        replace_expr(ast, id, folded != object, Expr::Get(folded, name))
+    }
+
+    fn fold_set(&mut self, ast: &mut Ast, id: ExprId, object: ExprId, name: Symbol, op: Token, value: ExprId) -> ExprId {
+        let (new_object, new_value) = (self.fold_expr(ast, object), self.fold_expr(ast, value));
+        replace_expr(ast, id, (new_object, new_value) != (object, value), Expr::Set(new_object, name, op, new_value))
+    }
+
+    fn fold_grouping(&mut self, ast: &mut Ast, id: ExprId, expr: ExprId) -> ExprId {
+        let folded = self.fold_expr(ast, expr);
+        replace_expr(ast, id, folded != expr, Expr::Grouping(folded))
+    }
+
+    fn fold_lambda(&mut self, ast: &mut Ast, id: ExprId, decl: FunctionId) -> ExprId {
+        let folded = self.fold_function(ast, decl);
+        replace_expr(ast, id, folded != decl, Expr::Lambda(folded))
+    }
+
+    fn fold_conditional(&mut self, ast: &mut Ast, id: ExprId, cond: ExprId, then: ExprId, otherwise: ExprId) -> ExprId {
+        let folded = (self.fold_expr(ast, cond), self.fold_expr(ast, then), self.fold_expr(ast, otherwise));
+        replace_expr(ast, id, folded != (cond, then, otherwise), Expr::Conditional(folded.0, folded.1, folded.2))
+    }
+
+    fn fold_literal(&mut self, _ast: &mut Ast, id: ExprId, _value: Atom) -> ExprId {
+        id
+    }
+
+    fn fold_unary(&mut self, ast: &mut Ast, id: ExprId, op: Token, rhs: ExprId) -> ExprId {
+        let folded = self.fold_expr(ast, rhs);
+        replace_expr(ast, id, folded != rhs, Expr::Unary(op, folded))
+    }
+
+    fn fold_var(&mut self, _ast: &mut Ast, id: ExprId, _name: Symbol) -> ExprId {
+        id
+    }
+}
+
+//hands the statement to the fold_* method for its kind
+pub fn walk_stmt<F: Fold + ?Sized>(folder: &mut F, ast: &mut Ast, id: StmtId) -> StmtId {
+    match ast.stmt(id).clone() {
+        Stmt::Block(stmts) => folder.fold_block_stmt(ast, id, stmts),
+        Stmt::Expr(expr) => folder.fold_expr_stmt(ast, id, expr),
+        Stmt::Function(decl, doc) => folder.fold_function_stmt(ast, id, decl, doc),
+        Stmt::Print(expr) => folder.fold_print_stmt(ast, id, expr),
+        Stmt::Return(expr) => folder.fold_return_stmt(ast, id, expr),
+        Stmt::Var(name, init, doc) => folder.fold_var_stmt(ast, id, name, init, doc)
+    }
+}
+
+//hands the expression to the fold_* method for its kind
+pub fn walk_expr<F: Fold + ?Sized>(folder: &mut F, ast: &mut Ast, id: ExprId) -> ExprId {
+    match ast.expr(id).clone() {
+        Expr::Assign(name, op, value) => folder.fold_assign(ast, id, name, op, value),
+        Expr::Binary(lhs, op, rhs) => folder.fold_binary(ast, id, lhs, op, rhs),
+        Expr::Call(callee, paren, args) => folder.fold_call(ast, id, callee, paren, args),
+        Expr::Get(object, name) => folder.fold_get(ast, id, object, name),
+        Expr::Set(object, name, op, value) => folder.fold_set(ast, id, object, name, op, value),
+        Expr::Grouping(expr) => folder.fold_grouping(ast, id, expr),
+        Expr::Lambda(decl) => folder.fold_lambda(ast, id, decl),
+        Expr::Conditional(cond, then, otherwise) => folder.fold_conditional(ast, id, cond, then, otherwise),
+        Expr::Literal(value) => folder.fold_literal(ast, id, value),
+        Expr::Unary(op, rhs) => folder.fold_unary(ast, id, op, rhs),
+        Expr::Var(name) => folder.fold_var(ast, id, name)
+    }
+}
+
+pub fn walk_function<F: Fold + ?Sized>(folder: &mut F, ast: &mut Ast, id: FunctionId) -> FunctionId {
+    let decl = ast.function(id).clone();
+    let body = fold_stmts(folder, ast, &decl.body);
+    if body == decl.body {
+        id
+    }else{
+        ast.add_function(FunctionDecl { body, ..decl })
+    }
+}
+
+pub fn fold_stmts<F: Fold + ?Sized>(folder: &mut F, ast: &mut Ast, stmts: &[StmtId]) -> Vec<StmtId> {
+    stmts.iter().map(|stmt| folder.fold_stmt(ast, *stmt)).collect()
+}
+
+//adds expr in place of the node at id when changed is set
+pub fn replace_expr(ast: &mut Ast, id: ExprId, changed: bool, expr: Expr) -> ExprId {
+    if !changed {
+        return id;
+    }
+
+    let new = ast.add_expr(expr, ast.expr_span(id));
+    if let Some(depth) = ast.local_depth(id) {
+        ast.resolve_local(new, depth);
+    }
+    new
+}
+
+pub fn replace_stmt(ast: &mut Ast, id: StmtId, changed: bool, stmt: Stmt) -> StmtId {
+    if changed {
+        ast.add_stmt(stmt, ast.stmt_span(id))
+    }else{
+        id
+    }
+}
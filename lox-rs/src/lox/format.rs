@@ -0,0 +1,559 @@
+use super::ast::{Ast, Expr, ExprId, FunctionId, Stmt, StmtId};
+use super::atom::Atom;
+use super::errors::LanguageError;
+use super::json;
+use super::parser::{self, Assoc, Parser, Precedence};
+use super::scanner::{Scanner, Token, TokenType};
+use super::symbol::Symbol;
+
+const INDENT: usize = 4;
+
+//formats Lox source, the output parses back to the same program. Comments
+//are kept on the line they were on relative to the statements around
+//them, a comment in the middle of a statement moves below it
+pub fn format(source: &str, width: usize) -> Result<String, Vec<LanguageError>> {
+    let (ast, stmts, tokens) = parse(source)?;
+    let formatted = layout(&ast, &stmts, comments(&tokens), width).map_err(|err| vec![err])?;
+
+    //a formatter bug must never change what a program does, or lose a
+    //comment
+    match parse(&formatted) {
+        Ok((new_ast, new_stmts, new_tokens)) if json::shape(&json::program(&new_ast, &new_stmts)) == json::shape(&json::program(&ast, &stmts))
+                                               && texts(&comments(&new_tokens)) == texts(&comments(&tokens)) => Ok(formatted),
+        _ => Err(vec![LanguageError::SyntaxError("Formatting would change the program".into())])
+    }
+}
+
+//writes out a program that didn't come from source, one built by hand or
+//loaded from JSON. There are no comments to keep and spans are ignored.
+//Parentheses are added wherever the tree needs them to parse back the
+//same. Fails on a string containing `"`, Lox has no way to write one
+pub fn program(ast: &Ast, stmts: &[StmtId], width: usize) -> Result<String, LanguageError> {
+    layout(ast, stmts, Vec::new(), width)
+}
+
+//writes a single function, `fun name(params) { ... }` or for a lambda
+//`fun (params) { ... }`
+pub fn function(ast: &Ast, id: FunctionId, width: usize) -> Result<String, LanguageError> {
+    let mut formatter = Formatter::new(ast, Vec::new());
+    let doc = formatter.function(id, i32::MAX);
+    formatter.finish(&doc, width)
+}
+
+//writes a function as a lambda even when it has a name
+pub fn lambda(ast: &Ast, id: FunctionId, width: usize) -> Result<String, LanguageError> {
+    let mut formatter = Formatter::new(ast, Vec::new());
+    let doc = formatter.named_function(id, None, i32::MAX);
+    formatter.finish(&doc, width)
+}
+
+fn layout(ast: &Ast, stmts: &[StmtId], comments: Vec<Comment>, width: usize) -> Result<String, LanguageError> {
+    let mut formatter = Formatter::new(ast, comments);
+    let mut lines = formatter.lines(stmts, i32::MAX);
+    lines.push(Doc::HardLine);
+    formatter.finish(&Doc::Concat(lines), width)
+}
+
+fn parse(source: &str) -> Result<(Ast, Vec<StmtId>, Vec<Token>), Vec<LanguageError>> {
+    let mut scanner = Scanner::lossless(source.to_string());
+    let tokens = match scanner.scan_tokens() {
+        Ok(tokens) => tokens.clone(),
+        Err(errs) => return Err(errs.clone())
+    };
+
+    let mut ast = Ast::default();
+    let mut parser = Parser::new(&tokens, &mut ast);
+    match parser.parse() {
+        Ok(stmts) => Ok((ast, stmts, tokens)),
+        Err(_) => Err(parser.errors().clone())
+    }
+}
+
+struct Comment {
+    line: i32,
+    //lines the comment itself spans beyond the first
+    extra_lines: i32,
+    text: String,
+    //there was code before it on the same line
+    trailing: bool,
+    //there was an empty line before it
+    blank_before: bool
+}
+
+//comments and doc comments in source order. Doc comments are kept as
+//comments too, so they end up above whatever they were above
+fn comments(tokens: &[Token]) -> Vec<Comment> {
+    let mut comments = Vec::new();
+    let mut line = 1;
+    let mut code_on_line = false;
+    let mut newlines_since = 2;
+
+    for token in tokens {
+        for (i, trivia) in [&token.leading_trivia, &token.lexeme, &token.trailing_trivia].into_iter().enumerate() {
+            if i == 1 {
+                if let TokenType::DOC(_) = token.token_type {
+                    comments.push(Comment { line, extra_lines: 0, text: trivia.trim_end().to_string(), trailing: code_on_line, blank_before: newlines_since > 1 });
+                    newlines_since = 0;
+                }else if !trivia.is_empty() {
+                    code_on_line = true;
+                    newlines_since = 0;
+                }
+                line += newlines(trivia);
+                continue;
+            }
+
+            let mut rest = trivia.as_str();
+            while let Some(c) = rest.chars().next() {
+                let len = if rest.starts_with("//") {
+                    rest.find('\n').unwrap_or(rest.len())
+                }else if rest.starts_with("/*") {
+                    block_comment_len(rest)
+                }else{
+                    if c == '\n' {
+                        line += 1;
+                        code_on_line = false;
+                        newlines_since += 1;
+                    }
+                    rest = &rest[c.len_utf8()..];
+                    continue;
+                };
+
+                let text = &rest[..len];
+                comments.push(Comment { line, extra_lines: newlines(text), text: text.trim_end().to_string(), trailing: code_on_line, blank_before: newlines_since > 1 });
+                line += newlines(text);
+                newlines_since = 0;
+                rest = &rest[len..];
+            }
+        }
+    }
+
+    comments
+}
+
+fn texts(comments: &[Comment]) -> Vec<&str> {
+    comments.iter().map(|comment| comment.text.as_str()).collect()
+}
+
+fn block_comment_len(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let (mut i, mut depth) = (2, 1);
+    while i < bytes.len() && depth > 0 {
+        match (bytes[i], bytes.get(i + 1)) {
+            (b'/', Some(b'*')) => { depth += 1; i += 1; },
+            (b'*', Some(b'/')) => { depth -= 1; i += 1; },
+            _ => {}
+        }
+        i += 1;
+    }
+    i.min(bytes.len())
+}
+
+fn newlines(text: &str) -> i32 {
+    text.matches('\n').count() as i32
+}
+
+struct Formatter<'a> {
+    ast: &'a Ast,
+    comments: Vec<Comment>,
+    //first comment not written yet
+    next: usize,
+    //last source line written, None at the start of a block
+    last_line: Option<i32>,
+    //a string literal that can't be written was met
+    unwritable: bool
+}
+
+impl<'a> Formatter<'a> {
+    fn new(ast: &'a Ast, comments: Vec<Comment>) -> Self {
+        Formatter { ast, comments, next: 0, last_line: None, unwritable: false }
+    }
+
+    fn finish(&self, doc: &Doc, width: usize) -> Result<String, LanguageError> {
+        if self.unwritable {
+            return Err(LanguageError::SyntaxError(String::from("A string containing \" can't be written as Lox source")));
+        }
+        Ok(render(doc, width))
+    }
+
+    //statements one per line, with the comments before end_line
+    fn lines(&mut self, stmts: &[StmtId], end_line: i32) -> Vec<Doc> {
+        let mut lines: Vec<Doc> = Vec::new();
+        for stmt in stmts {
+            let span = self.ast.stmt_span(*stmt);
+            self.comments_before(span.line, &mut lines);
+            if self.last_line.is_some_and(|last| span.line > last + 1) {
+                lines.push(Doc::HardLine);
+            }
+
+            let mut line = vec![self.stmt(*stmt)];
+            self.last_line = Some(span.end_line);
+            //a comment after a statement that ends on the block's closing
+            //line follows the `}`, it is left for the block
+            if let Some(comment) = self.comments.get(self.next) {
+                if comment.trailing && comment.line == span.end_line && span.end_line < end_line {
+                    line.push(Doc::text(format!(" {}", comment.text)));
+                    self.last_line = Some(comment.line + comment.extra_lines);
+                    self.next += 1;
+                }
+            }
+            if !lines.is_empty() {
+                lines.push(Doc::HardLine);
+            }
+            lines.push(Doc::Concat(line));
+        }
+        self.comments_before(end_line, &mut lines);
+        lines
+    }
+
+    fn comments_before(&mut self, line: i32, lines: &mut Vec<Doc>) {
+        while let Some(comment) = self.comments.get(self.next) {
+            if comment.line >= line {
+                break;
+            }
+            if !lines.is_empty() {
+                lines.push(Doc::HardLine);
+            }
+            if comment.blank_before && self.last_line.is_some() {
+                lines.push(Doc::HardLine);
+            }
+            lines.push(Doc::text(comment.text.clone()));
+            //a comment moved out of the statement before it can be above
+            //the last line written
+            let end = comment.line + comment.extra_lines;
+            self.last_line = Some(self.last_line.map_or(end, |last| last.max(end)));
+            self.next += 1;
+        }
+    }
+
+    fn block(&mut self, stmts: &[StmtId], end_line: i32) -> Doc {
+        self.last_line = None;
+        let lines = self.lines(stmts, end_line);
+        self.last_line = Some(end_line);
+        if lines.is_empty() {
+            return Doc::text("{}");
+        }
+
+        Doc::Concat(vec![
+            Doc::text("{"),
+            Doc::Break(Box::new(Doc::Concat(vec![
+                Doc::nest(Doc::Concat([Doc::HardLine].into_iter().chain(lines).collect())),
+                Doc::HardLine
+            ]))),
+            Doc::text("}")
+        ])
+    }
+
+    fn stmt(&mut self, id: StmtId) -> Doc {
+        let span = self.ast.stmt_span(id);
+        match self.ast.stmt(id) {
+            Stmt::Block(stmts) => self.block(stmts, span.end_line),
+            Stmt::Expr(expr) => Doc::Concat(vec![self.expr(*expr, true), Doc::text(";")]),
+            Stmt::Function(decl, _) => self.function(*decl, span.end_line),
+            Stmt::Print(expr) => Doc::Concat(vec![Doc::text("print "), self.expr(*expr, true), Doc::text(";")]),
+            Stmt::Return(expr) if is_nil(self.ast, *expr) => Doc::text("return;"),
+            Stmt::Return(expr) => Doc::Concat(vec![Doc::text("return "), self.expr(*expr, true), Doc::text(";")]),
+            Stmt::Var(name, init, _) if is_nil(self.ast, *init) => Doc::text(format!("var {};", name)),
+            Stmt::Var(name, init, _) => Doc::Concat(vec![Doc::text(format!("var {} = ", name)), self.expr(*init, true), Doc::text(";")])
+        }
+    }
+
+    //`fun name(params) { ... }`, or `fun (params) { ... }` for a lambda
+    fn function(&mut self, id: FunctionId, end_line: i32) -> Doc {
+        self.named_function(id, self.ast.function(id).name.clone(), end_line)
+    }
+
+    fn named_function(&mut self, id: FunctionId, name: Option<Symbol>, end_line: i32) -> Doc {
+        let decl = self.ast.function(id);
+        let name = name.map_or(String::from("fun ("), |name| format!("fun {}(", name));
+        let params = decl.params.iter().map(|p| Doc::text(p.to_string())).collect();
+        Doc::Concat(vec![
+            list(name, params, ")"),
+            Doc::text(" "),
+            self.block(&decl.body, end_line)
+        ])
+    }
+
+    //tail is set when nothing follows the expression before a `)`, `,`,
+    //`:` or `;`, only there can a lambda be written with an arrow, since
+    //the arrow's body would swallow anything after it
+    fn expr(&mut self, id: ExprId, tail: bool) -> Doc {
+        let ast = self.ast;
+        match ast.expr(id) {
+            Expr::Assign(name, op, value) => Doc::Concat(vec![
+                Doc::text(format!("{} {} ", name, op.lexeme)),
+                self.operand(*value, Precedence::Assignment, tail)
+            ]),
+            Expr::Binary(lhs, op, rhs) if op.token_type == TokenType::COMMA => Doc::group(Doc::Concat(vec![
+                self.operand(*lhs, Precedence::Comma, true),
+                Doc::text(","),
+                Doc::Line,
+                self.operand(*rhs, Precedence::Assignment, tail)
+            ])),
+            Expr::Binary(lhs, op, rhs) => {
+                let (lhs_min, rhs_min) = match parser::binding(&op.token_type) {
+                    (precedence, Assoc::Left) => (precedence, precedence.next()),
+                    (precedence, Assoc::Right) => (precedence.next(), precedence)
+                };
+                Doc::group(Doc::Concat(vec![
+                    self.operand(*lhs, lhs_min, false),
+                    Doc::text(format!(" {}", op.lexeme)),
+                    Doc::nest(Doc::Concat(vec![Doc::Line, self.operand(*rhs, rhs_min, tail)]))
+                ]))
+            },
+            Expr::Call(callee, _, args) => {
+                let callee = self.operand(*callee, Precedence::Call, false);
+                let args = args.iter().map(|arg| self.operand(*arg, Precedence::Assignment, true)).collect();
+                Doc::Concat(vec![callee, list(String::from("("), args, ")")])
+            },
+            Expr::Get(object, name) => Doc::Concat(vec![self.operand(*object, Precedence::Call, false), Doc::text(format!(".{}", name))]),
+            Expr::Set(object, name, op, value) => Doc::Concat(vec![
+                self.operand(*object, Precedence::Call, false),
+                Doc::text(format!(".{} {} ", name, op.lexeme)),
+                self.operand(*value, Precedence::Assignment, tail)
+            ]),
+            Expr::Grouping(inner) => Doc::Concat(vec![Doc::text("("), self.expr(*inner, true), Doc::text(")")]),
+            Expr::Lambda(decl) => self.lambda(id, *decl, tail),
+            Expr::Conditional(cond, then, otherwise) => Doc::group(Doc::Concat(vec![
+                self.operand(*cond, Precedence::Conditional.next(), false),
+                Doc::nest(Doc::Concat(vec![
+                    Doc::Line,
+                    Doc::text("? "),
+                    self.expr(*then, true),
+                    Doc::Line,
+                    Doc::text(": "),
+                    self.operand(*otherwise, Precedence::Conditional, tail)
+                ]))
+            ])),
+            Expr::Literal(atom) => match literal(atom) {
+                Some(text) => Doc::text(text),
+                None => {
+                    self.unwritable = true;
+                    Doc::text("nil")
+                }
+            },
+            Expr::Unary(op, rhs) => Doc::Concat(vec![Doc::text(op.lexeme.clone()), self.operand(*rhs, Precedence::Unary, tail)]),
+            Expr::Var(name) => Doc::text(name.to_string())
+        }
+    }
+
+    //an expression where the parser expects one binding at least as tight
+    //as min, in parentheses when it doesn't. A tree parsed from source
+    //already has a Grouping wherever one is needed
+    fn operand(&mut self, id: ExprId, min: Precedence, tail: bool) -> Doc {
+        if !binds_within(self.ast, id, min) {
+            return Doc::Concat(vec![Doc::text("("), self.expr(id, true), Doc::text(")")]);
+        }
+        self.expr(id, tail)
+    }
+
+    //a lambda that only returns an expression is written `(params) => expr`
+    fn lambda(&mut self, id: ExprId, decl: FunctionId, tail: bool) -> Doc {
+        let ast = self.ast;
+        let function = ast.function(decl);
+        let value = match function.body.as_slice() {
+            [stmt] => match ast.stmt(*stmt) {
+                Stmt::Return(value) if !is_comma(ast, *value) => Some(*value),
+                _ => None
+            },
+            _ => None
+        };
+
+        match value {
+            Some(value) if tail && !self.has_comments_within(ast.expr_span(id).line, ast.expr_span(id).end_line) => {
+                let params = function.params.iter().map(|p| Doc::text(p.to_string())).collect();
+                Doc::Concat(vec![
+                    list(String::from("("), params, ")"),
+                    Doc::text(" => "),
+                    self.expr(value, true)
+                ])
+            },
+            _ => self.function(decl, ast.expr_span(id).end_line)
+        }
+    }
+
+    //an arrow lambda has no statements to keep comments next to
+    fn has_comments_within(&self, line: i32, end_line: i32) -> bool {
+        self.comments[self.next..].iter().any(|comment| comment.line > line && comment.line < end_line)
+    }
+}
+
+fn is_nil(ast: &Ast, expr: ExprId) -> bool {
+    matches!(ast.expr(expr), Expr::Literal(Atom::Nil))
+}
+
+fn is_comma(ast: &Ast, expr: ExprId) -> bool {
+    matches!(ast.expr(expr), Expr::Binary(_, op, _) if op.token_type == TokenType::COMMA)
+}
+
+//whether the expression parses back the same where the parser expects
+//one binding at least as tight as min
+fn binds_within(ast: &Ast, expr: ExprId, min: Precedence) -> bool {
+    match ast.expr(expr) {
+        Expr::Binary(_, op, _) => parser::binding(&op.token_type).0 >= min,
+        Expr::Assign(..) | Expr::Set(..) => Precedence::Assignment >= min,
+        Expr::Conditional(..) => Precedence::Conditional >= min,
+        //a prefix operator can start any operand, but takes an exponent,
+        //call or property after it as its own
+        Expr::Unary(..) => min <= Precedence::Exponent,
+        Expr::Literal(Atom::Number(n)) if n.is_finite() && n.is_sign_negative() => min <= Precedence::Exponent,
+        _ => true
+    }
+}
+
+//a literal written as Lox source. Numbers that have no literal are
+//written as the division giving them. None for a string containing `"`,
+//Lox strings have no escapes
+pub fn literal(atom: &Atom) -> Option<String> {
+    Some(match atom {
+        Atom::String(s) if s.contains('"') => return None,
+        Atom::String(s) => format!("\"{}\"", s),
+        Atom::Number(n) if n.is_nan() => String::from("(0 / 0)"),
+        Atom::Number(n) if n.is_infinite() => String::from(if *n > 0.0 { "(1 / 0)" } else { "(-1 / 0)" }),
+        Atom::Number(n) => format!("{}", n),
+        Atom::Bool(b) => format!("{}", b),
+        _ => String::from("nil")
+    })
+}
+
+//`open a, b close`, broken one item per line when it doesn't fit
+fn list(open: String, items: Vec<Doc>, close: &str) -> Doc {
+    if items.is_empty() {
+        return Doc::text(open + close);
+    }
+
+    let mut inner = vec![Doc::SoftLine];
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            inner.push(Doc::text(","));
+            inner.push(Doc::Line);
+        }
+        inner.push(item);
+    }
+    Doc::group(Doc::Concat(vec![
+        Doc::text(open),
+        Doc::nest(Doc::Concat(inner)),
+        Doc::SoftLine,
+        Doc::text(close)
+    ]))
+}
+
+//the layout is built as a document of text and possible line breaks and
+//laid out to a width afterwards. A group is written on one line when it
+//fits, otherwise every Line and SoftLine directly in it breaks. Only a
+//broken group indents what's nested in it, so a block inside a call that
+//stays on one line is indented from the start of the line
+enum Doc {
+    Text(String),
+    //a space, or a line break
+    Line,
+    //nothing, or a line break
+    SoftLine,
+    HardLine,
+    Concat(Vec<Doc>),
+    Nest(Box<Doc>),
+    Group(Box<Doc>),
+    //always laid out broken, the body of a block
+    Break(Box<Doc>)
+}
+
+impl Doc {
+    fn text(text: impl Into<String>) -> Doc {
+        Doc::Text(text.into())
+    }
+
+    fn nest(doc: Doc) -> Doc {
+        Doc::Nest(Box::new(doc))
+    }
+
+    fn group(doc: Doc) -> Doc {
+        Doc::Group(Box::new(doc))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Flat,
+    Break
+}
+
+fn render(doc: &Doc, width: usize) -> String {
+    let mut out = String::new();
+    let mut column = 0;
+    //indentation is only written once something follows on the line, so
+    //empty lines have no trailing whitespace
+    let mut pending_indent: Option<usize> = None;
+    let mut stack: Vec<(usize, Mode, &Doc)> = vec![(0, Mode::Break, doc)];
+
+    while let Some((indent, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(text) => {
+                if let Some(indent) = pending_indent.take() {
+                    out.push_str(&" ".repeat(indent));
+                    column = indent;
+                }
+                out.push_str(text);
+                column = match text.rfind('\n') {
+                    Some(i) => text.len() - i - 1,
+                    None => column + text.chars().count()
+                };
+            },
+            Doc::Line if mode == Mode::Flat => {
+                out.push(' ');
+                column += 1;
+            },
+            Doc::SoftLine if mode == Mode::Flat => {},
+            Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                out.push('\n');
+                column = 0;
+                pending_indent = Some(indent);
+            },
+            Doc::Concat(docs) => {
+                for doc in docs.iter().rev() {
+                    stack.push((indent, mode, doc));
+                }
+            },
+            Doc::Nest(doc) if mode == Mode::Flat => stack.push((indent, mode, doc)),
+            Doc::Nest(doc) => stack.push((indent + INDENT, mode, doc)),
+            Doc::Group(inner) => {
+                let start = pending_indent.unwrap_or(column);
+                let mode = if fits(width as isize - start as isize, inner, &stack) { Mode::Flat } else { Mode::Break };
+                stack.push((indent, mode, inner));
+            },
+            Doc::Break(inner) => stack.push((indent, Mode::Break, inner))
+        }
+    }
+
+    out
+}
+
+//whether the group, written flat, and whatever follows it up to the next
+//line break fit in the remaining width. The rest of the stack is only
+//walked until either is known, so this stays cheap on long files
+fn fits(mut remaining: isize, group: &Doc, rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut rest = rest.iter().rev().map(|(_, mode, doc)| (*mode, *doc));
+    let mut docs: Vec<(Mode, &Doc)> = vec![(Mode::Flat, group)];
+
+    while let Some((mode, doc)) = docs.pop().or_else(|| rest.next()) {
+        if remaining < 0 {
+            return false;
+        }
+        match doc {
+            Doc::Text(text) => match text.find('\n') {
+                Some(i) => return remaining >= i as isize,
+                None => remaining -= text.chars().count() as isize
+            },
+            Doc::Line if mode == Mode::Flat => remaining -= 1,
+            Doc::SoftLine if mode == Mode::Flat => {},
+            Doc::Line | Doc::SoftLine | Doc::HardLine => return true,
+            Doc::Concat(inner) => {
+                for doc in inner.iter().rev() {
+                    docs.push((mode, doc));
+                }
+            },
+            Doc::Nest(inner) | Doc::Group(inner) => docs.push((mode, inner)),
+            Doc::Break(inner) => docs.push((Mode::Break, inner))
+        }
+    }
+
+    remaining >= 0
+}
@@ -0,0 +1,146 @@
+use super::atom::Atom;
+use super::errors::LanguageError;
+use super::native::{Arity, IntoAtom, Native};
+use super::symbol::Symbol;
+
+use std::cell::{Ref, RefCell, RefMut};
+use std::fmt;
+use std::rc::Rc;
+
+//a Rust value scripts can use through `.`, `object.field`,
+//`object.field = value` and `object.method(args)`. Only type_name has to
+//be written, an object without the rest simply has no properties or
+//methods
+pub trait HostObject {
+    fn type_name(&self) -> &'static str;
+
+    //None when there is no such property
+    fn get(&self, _name: &str) -> Option<Atom> {
+        None
+    }
+
+    fn set(&mut self, name: &str, _value: Atom) -> Result<(), LanguageError> {
+        Err(LanguageError::RuntimeError(format!("{} has no property {}", self.type_name(), name)))
+    }
+
+    fn has_method(&self, _name: &str) -> bool {
+        false
+    }
+
+    //only called for names has_method accepts, the arguments are not
+    //checked beforehand
+    fn call_method(&mut self, name: &str, _args: Vec<Atom>) -> Result<Atom, LanguageError> {
+        Err(LanguageError::RuntimeError(format!("{} has no method {}", self.type_name(), name)))
+    }
+}
+
+//a host object shared between Rust and Lox. Clones are the same object,
+//so the host sees every change a script makes and the other way around
+#[derive(Clone)]
+pub struct Object {
+    inner: Rc<RefCell<dyn HostObject>>
+}
+
+impl Object {
+    pub fn new(value: Rc<RefCell<dyn HostObject>>) -> Self {
+        Object { inner: value }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        self.borrow().map_or("Object", |object| object.type_name())
+    }
+
+    pub fn ptr_eq(&self, other: &Object) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    //a property, or a method bound to this object
+    pub fn get(&self, name: &Symbol) -> Result<Atom, LanguageError> {
+        let object = self.borrow()?;
+        if let Some(value) = object.get(name.name()) {
+            return Ok(value);
+        }
+        if !object.has_method(name.name()) {
+            return Err(LanguageError::RuntimeError(format!("Undefined property {} on {}", name, object.type_name())));
+        }
+
+        let this = self.clone();
+        let arity = Arity { fixed: 0, rest: true };
+        let method = name.clone();
+        Ok(Atom::Native(Rc::new(Native::new(name.clone(), arity, move |args| {
+            this.borrow_mut()?.call_method(method.name(), args)
+        }))))
+    }
+
+    pub fn set(&self, name: &Symbol, value: Atom) -> Result<(), LanguageError> {
+        self.borrow_mut()?.set(name.name(), value)
+    }
+
+    //a method handed its own object can't borrow it again
+    fn borrow(&self) -> Result<Ref<'_, dyn HostObject + 'static>, LanguageError> {
+        self.inner.try_borrow().map_err(|_| in_use())
+    }
+
+    fn borrow_mut(&self) -> Result<RefMut<'_, dyn HostObject + 'static>, LanguageError> {
+        self.inner.try_borrow_mut().map_err(|_| in_use())
+    }
+}
+
+fn in_use() -> LanguageError {
+    LanguageError::RuntimeError(String::from("Object is already in use"))
+}
+
+impl fmt::Debug for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<instance {}>", self.type_name())
+    }
+}
+
+//the host keeps its own clone of the Rc to see what scripts do
+impl<T: HostObject + 'static> IntoAtom for Rc<RefCell<T>> {
+    fn into_atom(self) -> Atom {
+        Atom::Object(Object::new(self))
+    }
+}
+
+//what a native returning a Vec hands to Lox, which has no lists of its
+//own: `list.length` and `list.get(index)`
+pub struct List {
+    items: Vec<Atom>
+}
+
+impl HostObject for List {
+    fn type_name(&self) -> &'static str {
+        "List"
+    }
+
+    fn get(&self, name: &str) -> Option<Atom> {
+        match name {
+            "length" => Some(Atom::Number(self.items.len() as f64)),
+            _ => None
+        }
+    }
+
+    fn has_method(&self, name: &str) -> bool {
+        name == "get"
+    }
+
+    fn call_method(&mut self, _name: &str, args: Vec<Atom>) -> Result<Atom, LanguageError> {
+        match args.as_slice() {
+            [Atom::Number(index)] if index.fract() == 0.0 && *index >= 0.0 && (*index as usize) < self.items.len() => {
+                Ok(self.items[*index as usize].clone())
+            },
+            [Atom::Number(index)] => {
+                Err(LanguageError::RuntimeError(format!("List index {} is out of range for length {}", index, self.items.len())))
+            },
+            _ => Err(LanguageError::RuntimeError(String::from("List.get takes one Number")))
+        }
+    }
+}
+
+impl<T: IntoAtom> IntoAtom for Vec<T> {
+    fn into_atom(self) -> Atom {
+        let items = self.into_iter().map(T::into_atom).collect();
+        Rc::new(RefCell::new(List { items })).into_atom()
+    }
+}
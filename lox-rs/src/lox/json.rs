@@ -0,0 +1,686 @@
+use super::ast::{Ast, Expr, ExprId, FunctionDecl, FunctionId, Span, Stmt, StmtId};
+use super::atom::Atom;
+use super::errors::LanguageError;
+use super::parser::MAX_DEPTH;
+use super::scanner::{Scanner, Token, TokenType};
+use super::symbol::Symbol;
+
+use std::fmt;
+
+//how deep JSON and S-expressions may nest when read. A node of the tree
+//sits at most three levels below its parent, an arrow lambda's value is in
+//a Return in the body array, so any tree within the parser's limit fits
+pub const MAX_NESTING: usize = 4 * MAX_DEPTH;
+
+//just enough JSON to dump the front end stages
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>)
+}
+
+impl Json {
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Json, String> {
+        let mut reader = Reader { chars: source.chars().collect(), current: 0, depth: 0 };
+        let json = reader.value()?;
+        reader.skip_whitespace();
+        if reader.current < reader.chars.len() {
+            return Err(format!("Unexpected {:?} after JSON value", reader.chars[reader.current]));
+        }
+        Ok(json)
+    }
+}
+
+struct Reader {
+    chars: Vec<char>,
+    current: usize,
+    //arrays and objects we are in
+    depth: usize
+}
+
+impl Reader {
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.advance() {
+            Some('n') => self.keyword("ull", Json::Null),
+            Some('t') => self.keyword("rue", Json::Bool(true)),
+            Some('f') => self.keyword("alse", Json::Bool(false)),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('[') => self.nested(Self::array),
+            Some('{') => self.nested(Self::object),
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let start = self.current - 1;
+                while matches!(self.peek(), Some(c) if c.is_ascii_digit() || "+-.eE".contains(c)) {
+                    self.advance();
+                }
+                let text: String = self.chars[start..self.current].iter().collect();
+                text.parse::<f64>().map(Json::Number).map_err(|_| format!("Invalid number {}", text))
+            },
+            Some(c) => Err(format!("Unexpected {:?}", c)),
+            None => Err("Unexpected end of JSON".into())
+        }
+    }
+
+    fn nested(&mut self, read: fn(&mut Self) -> Result<Json, String>) -> Result<Json, String> {
+        if self.depth >= MAX_NESTING {
+            return Err(format!("Nesting deeper than {} levels", MAX_NESTING));
+        }
+        self.depth += 1;
+        let json = read(self);
+        self.depth -= 1;
+        json
+    }
+
+    //everything after the opening bracket
+    fn array(&mut self) -> Result<Json, String> {
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.advance();
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.advance() {
+                Some(',') => continue,
+                Some(']') => return Ok(Json::Array(items)),
+                _ => return Err("Expected , or ] in array".into())
+            }
+        }
+    }
+
+    //everything after the opening brace
+    fn object(&mut self) -> Result<Json, String> {
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.advance();
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.advance() != Some('"') {
+                return Err("Expected a string key in object".into());
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if self.advance() != Some(':') {
+                return Err("Expected : after object key".into());
+            }
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.advance() {
+                Some(',') => continue,
+                Some('}') => return Ok(Json::Object(fields)),
+                _ => return Err("Expected , or } in object".into())
+            }
+        }
+    }
+
+    fn keyword(&mut self, rest: &str, json: Json) -> Result<Json, String> {
+        for expected in rest.chars() {
+            if self.advance() != Some(expected) {
+                return Err("Invalid literal".into());
+            }
+        }
+        Ok(json)
+    }
+
+    //everything after the opening quote
+    fn string(&mut self) -> Result<String, String> {
+        let mut s = String::new();
+        loop {
+            match self.advance() {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.advance() {
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('u') => {
+                        let mut code = self.hex()?;
+                        //surrogate pairs come as two escapes, a high one
+                        //then a low one. Either on its own is an error
+                        if (0xd800..0xdc00).contains(&code) {
+                            if self.advance() != Some('\\') || self.advance() != Some('u') {
+                                return Err("Unpaired surrogate".into());
+                            }
+                            let low = self.hex()?;
+                            if !(0xdc00..0xe000).contains(&low) {
+                                return Err("Unpaired surrogate".into());
+                            }
+                            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                        }
+                        s.push(char::from_u32(code).ok_or("Unpaired surrogate")?);
+                    },
+                    Some(c) => s.push(c),
+                    None => return Err("Unterminated string".into())
+                },
+                Some(c) => s.push(c),
+                None => return Err("Unterminated string".into())
+            }
+        }
+    }
+
+    //exactly four hex digits, from_str_radix alone would take a sign
+    fn hex(&mut self) -> Result<u32, String> {
+        let digits: String = (0..4).filter_map(|_| self.advance()).collect();
+        if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Invalid unicode escape {}", digits));
+        }
+        u32::from_str_radix(&digits, 16).map_err(|_| format!("Invalid unicode escape {}", digits))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_whitespace()) {
+            self.advance();
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.current).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.current += 1;
+        c
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) if n.is_finite() => write!(f, "{}", n),
+            Json::Number(_) => write!(f, "null"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            },
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for ch in s.chars() {
+        match ch {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?
+        }
+    }
+    write!(f, "\"")
+}
+
+//NUMBER(1.0) -> NUMBER
+pub fn token_type_name(token_type: &TokenType) -> String {
+    let name = format!("{:?}", token_type);
+    name.split('(').next().unwrap_or_default().to_string()
+}
+
+impl From<&Atom> for Json {
+    fn from(atom: &Atom) -> Self {
+        match atom {
+            Atom::String(s) => Json::String(s.clone()),
+            Atom::Number(n) if n.is_finite() => Json::Number(*n),
+            Atom::Number(n) => Json::object(vec![("number", Json::String(match *n {
+                n if n.is_nan() => "NaN".into(),
+                n if n > 0.0 => "Infinity".into(),
+                _ => "-Infinity".into()
+            }))]),
+            Atom::Bool(b) => Json::Bool(*b),
+            Atom::Function(fun) => Json::String(format!("{:?}", fun)),
+            Atom::Native(native) => Json::String(format!("{:?}", native)),
+            Atom::Object(object) => Json::String(format!("{:?}", object)),
+            Atom::Nil => Json::Null
+        }
+    }
+}
+
+impl From<&Token> for Json {
+    fn from(token: &Token) -> Self {
+        let literal = match &token.token_type {
+            TokenType::IDENTIFIER(name) => Json::String(name.to_string()),
+            TokenType::STRING(s) | TokenType::DOC(s) => Json::String(s.clone()),
+            TokenType::NUMBER(n) => Json::Number(*n),
+            _ => Json::Null
+        };
+
+        Json::object(vec![
+            ("type", Json::String(token_type_name(&token.token_type))),
+            ("lexeme", Json::String(token.lexeme.clone())),
+            ("literal", literal),
+            ("line", Json::Number(token.line as f64))
+        ])
+    }
+}
+
+//the JSON schema is described in AST.md, load() reads back anything these
+//produce
+pub fn expr(ast: &Ast, id: ExprId) -> Json {
+    let span = ast.expr_span(id);
+    match ast.expr(id) {
+        Expr::Assign(name, op, value) => node("Assign", span, vec![
+            ("name", Json::String(name.to_string())),
+            ("operator", Json::from(op)),
+            ("value", expr(ast, *value))
+        ]),
+        Expr::Binary(lhs, op, rhs) => node("Binary", span, vec![
+            ("left", expr(ast, *lhs)),
+            ("operator", Json::from(op)),
+            ("right", expr(ast, *rhs))
+        ]),
+        Expr::Call(callee, paren, args) => node("Call", span, vec![
+            ("callee", expr(ast, *callee)),
+            ("paren", Json::from(paren)),
+            ("arguments", Json::Array(args.iter().map(|arg| expr(ast, *arg)).collect()))
+        ]),
+        Expr::Get(object, name) => node("Get", span, vec![
+            ("object", expr(ast, *object)),
+            ("name", Json::String(name.to_string()))
+        ]),
+        Expr::Set(object, name, op, value) => node("Set", span, vec![
+            ("object", expr(ast, *object)),
+            ("name", Json::String(name.to_string())),
+            ("operator", Json::from(op)),
+            ("value", expr(ast, *value))
+        ]),
+        Expr::Lambda(decl) => function(ast, "Lambda", span, *decl, None),
+        Expr::Grouping(inner) => node("Grouping", span, vec![
+            ("expression", expr(ast, *inner))
+        ]),
+        Expr::Conditional(cond, then, otherwise) => node("Conditional", span, vec![
+            ("condition", expr(ast, *cond)),
+            ("then", expr(ast, *then)),
+            ("else", expr(ast, *otherwise))
+        ]),
+        Expr::Literal(atom) => node("Literal", span, vec![
+            ("value", Json::from(atom))
+        ]),
+        Expr::Unary(op, rhs) => node("Unary", span, vec![
+            ("operator", Json::from(op)),
+            ("right", expr(ast, *rhs))
+        ]),
+        Expr::Var(name) => node("Var", span, vec![
+            ("name", Json::String(name.to_string()))
+        ])
+    }
+}
+
+pub fn stmt(ast: &Ast, id: StmtId) -> Json {
+    let span = ast.stmt_span(id);
+    match ast.stmt(id) {
+        Stmt::Block(stmts) => node("Block", span, vec![
+            ("statements", Json::Array(stmts.iter().map(|s| stmt(ast, *s)).collect()))
+        ]),
+        Stmt::Function(decl, doc) => function(ast, "Function", span, *decl, doc.as_ref()),
+        Stmt::Return(value) => node("Return", span, vec![
+            ("value", expr(ast, *value))
+        ]),
+        Stmt::Expr(inner) => node("Expression", span, vec![
+            ("expression", expr(ast, *inner))
+        ]),
+        Stmt::Print(inner) => node("Print", span, vec![
+            ("expression", expr(ast, *inner))
+        ]),
+        Stmt::Var(name, init, doc) => node("Var", span, vec![
+            ("name", Json::String(name.to_string())),
+            ("initializer", expr(ast, *init)),
+            ("doc", doc.clone().map_or(Json::Null, Json::String))
+        ])
+    }
+}
+
+pub fn program(ast: &Ast, stmts: &[StmtId]) -> Json {
+    Json::Array(stmts.iter().map(|s| stmt(ast, *s)).collect())
+}
+
+//the tree without source positions
+pub fn shape(json: &Json) -> Json {
+    match json {
+        Json::Object(fields) => Json::Object(fields.iter()
+                                    .filter(|(key, _)| key != "span" && key != "line")
+                                    .map(|(key, value)| (key.clone(), shape(value)))
+                                    .collect()),
+        Json::Array(items) => Json::Array(items.iter().map(shape).collect()),
+        other => other.clone()
+    }
+}
+
+fn node(kind: &str, span: Span, fields: Vec<(&str, Json)>) -> Json {
+    let mut node = vec![
+        ("type", Json::String(kind.into())),
+        ("span", Json::object(vec![
+            ("line", Json::Number(span.line as f64)),
+            ("end_line", Json::Number(span.end_line as f64))
+        ]))
+    ];
+    node.extend(fields);
+    Json::object(node)
+}
+
+fn function(ast: &Ast, kind: &str, span: Span, decl: FunctionId, doc: Option<&String>) -> Json {
+    let decl = ast.function(decl);
+    let mut fields = vec![
+        ("name", decl.name.as_ref().map_or(Json::Null, |name| Json::String(name.to_string()))),
+        ("params", Json::Array(decl.params.iter().map(|p| Json::String(p.to_string())).collect())),
+        ("body", Json::Array(decl.body.iter().map(|s| stmt(ast, *s)).collect()))
+    ];
+    if kind == "Function" {
+        fields.push(("doc", doc.cloned().map_or(Json::Null, Json::String)));
+    }
+
+    node(kind, span, fields)
+}
+
+const BINARY_OPERATORS: &[TokenType] = &[
+    TokenType::COMMA, TokenType::PLUS, TokenType::MINUS, TokenType::STAR, TokenType::SLASH,
+    TokenType::PERCENT, TokenType::TILDESLASH, TokenType::STARSTAR, TokenType::EQUALEQUAL,
+    TokenType::BANGEQUAL, TokenType::LESS, TokenType::LESSEQUAL, TokenType::GREATER, TokenType::GREATEREQUAL
+];
+
+const UNARY_OPERATORS: &[TokenType] = &[TokenType::MINUS, TokenType::BANG];
+
+const ASSIGN_OPERATORS: &[TokenType] = &[
+    TokenType::EQUAL, TokenType::PLUSEQUAL, TokenType::MINUSEQUAL, TokenType::STAREQUAL,
+    TokenType::SLASHEQUAL, TokenType::PERCENTEQUAL
+];
+
+//builds an Ast back from an array of statements in the schema above. The
+//input may be hand written, so everything the parser would have refused
+//is refused here too
+pub fn load(json: &Json) -> Result<(Ast, Vec<StmtId>), String> {
+    let mut loader = Loader { ast: Ast::default(), depth: 0, functions: 0 };
+    let stmts = loader.stmts(json, "program")?;
+
+    Ok((loader.ast, stmts))
+}
+
+struct Loader {
+    ast: Ast,
+    //expressions, blocks and functions we are in, limited like the parser
+    depth: usize,
+    //return is only allowed inside a function
+    functions: usize
+}
+
+impl Loader {
+    fn stmts(&mut self, json: &Json, what: &str) -> Result<Vec<StmtId>, String> {
+        array(json, what)?
+            .iter()
+            .map(|item| self.stmt(item))
+            .collect()
+    }
+
+    fn stmt(&mut self, json: &Json) -> Result<StmtId, String> {
+        let stmt = match string(field(json, "type")?, "type")? {
+            "Block" => Stmt::Block(self.nested(|loader| loader.stmts(field(json, "statements")?, "statements"))?),
+            "Function" => Stmt::Function(self.nested(|loader| loader.function(json))?, doc(json)?),
+            "Return" if self.functions == 0 => return Err("Can't return from top-level code".into()),
+            "Return" => Stmt::Return(self.child(json, "value")?),
+            "Expression" => Stmt::Expr(self.child(json, "expression")?),
+            "Print" => Stmt::Print(self.child(json, "expression")?),
+            "Var" => Stmt::Var(symbol(field(json, "name")?)?, self.child(json, "initializer")?, doc(json)?),
+            other => return Err(format!("Unknown statement type {}", other))
+        };
+
+        Ok(self.ast.add_stmt(stmt, load_span(json)?))
+    }
+
+    fn expr(&mut self, json: &Json) -> Result<ExprId, String> {
+        let expr = self.nested(|loader| loader.expr_node(json))?;
+        Ok(self.ast.add_expr(expr, load_span(json)?))
+    }
+
+    //the expression in the given field
+    fn child(&mut self, json: &Json, key: &str) -> Result<ExprId, String> {
+        self.expr(field(json, key)?)
+    }
+
+    //every kind has its own method so the recursion through here stays
+    //cheap on the stack, even in a debug build
+    fn expr_node(&mut self, json: &Json) -> Result<Expr, String> {
+        match string(field(json, "type")?, "type")? {
+            "Assign" => self.assign(json),
+            "Binary" => self.binary(json),
+            "Call" => self.call(json),
+            "Get" => Ok(Expr::Get(self.child(json, "object")?, symbol(field(json, "name")?)?)),
+            "Set" => self.set(json),
+            "Lambda" => Ok(Expr::Lambda(self.function(json)?)),
+            "Grouping" => Ok(Expr::Grouping(self.child(json, "expression")?)),
+            "Conditional" => self.conditional(json),
+            "Literal" => Ok(Expr::Literal(literal(field(json, "value")?)?)),
+            "Unary" => self.unary(json),
+            "Var" => Ok(Expr::Var(symbol(field(json, "name")?)?)),
+            other => Err(format!("Unknown expression type {}", other))
+        }
+    }
+
+    fn assign(&mut self, json: &Json) -> Result<Expr, String> {
+        let name = symbol(field(json, "name")?)?;
+        let op = operator(field(json, "operator")?, ASSIGN_OPERATORS, "an assignment")?;
+        Ok(Expr::Assign(name, op, self.child(json, "value")?))
+    }
+
+    fn binary(&mut self, json: &Json) -> Result<Expr, String> {
+        let op = operator(field(json, "operator")?, BINARY_OPERATORS, "a binary")?;
+        let left = self.child(json, "left")?;
+        Ok(Expr::Binary(left, op, self.child(json, "right")?))
+    }
+
+    fn call(&mut self, json: &Json) -> Result<Expr, String> {
+        let paren = operator(field(json, "paren")?, &[TokenType::RIGHTPAREN], "a closing paren")?;
+        let callee = self.child(json, "callee")?;
+        let args = array(field(json, "arguments")?, "arguments")?
+                    .iter()
+                    .map(|item| self.expr(item))
+                    .collect::<Result<Vec<ExprId>, String>>()?;
+        Ok(Expr::Call(callee, paren, args))
+    }
+
+    fn set(&mut self, json: &Json) -> Result<Expr, String> {
+        let name = symbol(field(json, "name")?)?;
+        let op = operator(field(json, "operator")?, ASSIGN_OPERATORS, "an assignment")?;
+        let object = self.child(json, "object")?;
+        Ok(Expr::Set(object, name, op, self.child(json, "value")?))
+    }
+
+    fn conditional(&mut self, json: &Json) -> Result<Expr, String> {
+        let condition = self.child(json, "condition")?;
+        let then = self.child(json, "then")?;
+        Ok(Expr::Conditional(condition, then, self.child(json, "else")?))
+    }
+
+    fn unary(&mut self, json: &Json) -> Result<Expr, String> {
+        let op = operator(field(json, "operator")?, UNARY_OPERATORS, "a unary")?;
+        Ok(Expr::Unary(op, self.child(json, "right")?))
+    }
+
+    fn function(&mut self, json: &Json) -> Result<FunctionId, String> {
+        let name = match field(json, "name")? {
+            Json::Null => None,
+            other => Some(symbol(other)?)
+        };
+        let params = array(field(json, "params")?, "params")?
+                        .iter()
+                        .map(symbol)
+                        .collect::<Result<Vec<Symbol>, String>>()?;
+        self.functions += 1;
+        let body = self.stmts(field(json, "body")?, "body");
+        self.functions -= 1;
+
+        Ok(self.ast.add_function(FunctionDecl { name, params, body: body? }))
+    }
+
+    //one level deeper, an error past the parser's limit so hostile input
+    //can't overflow the stack here or in any pass after
+    fn nested<T>(&mut self, load: impl FnOnce(&mut Self) -> Result<T, String>) -> Result<T, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(format!("Nesting deeper than {} levels", MAX_DEPTH));
+        }
+        self.depth += 1;
+        let result = load(self);
+        self.depth -= 1;
+        result
+    }
+}
+
+//the token type is worked out again from the lexeme, and has to agree with
+//the one given
+fn load_token(json: &Json) -> Result<Token, String> {
+    let lexeme = string(field(json, "lexeme")?, "lexeme")?;
+    let kind = string(field(json, "type")?, "type")?;
+    let token_type = match scan(lexeme) {
+        Some(token_type) => token_type,
+        None => return Err(format!("Invalid lexeme {:?}", lexeme))
+    };
+    if token_type_name(&token_type) != kind {
+        return Err(format!("Lexeme {:?} is not a {}", lexeme, kind));
+    }
+
+    let line = match json.get("line") {
+        Some(line) => number(line, "line")? as i32,
+        None => 0
+    };
+    Ok(Token { token_type, lexeme: lexeme.to_string(), line, leading_trivia: String::new(), trailing_trivia: String::new() })
+}
+
+//a token that has to be one of the given operators
+fn operator(json: &Json, allowed: &[TokenType], what: &str) -> Result<Token, String> {
+    let token = load_token(json)?;
+    if !allowed.contains(&token.token_type) {
+        return Err(format!("{:?} is not {} operator", token.lexeme, what));
+    }
+    Ok(token)
+}
+
+//the type of the single token the text scans to
+fn scan(text: &str) -> Option<TokenType> {
+    let mut scanner = Scanner::new(text.to_string());
+    match scanner.scan_tokens().map(|tokens| tokens.as_slice()) {
+        Ok([token, _eof]) if token.lexeme == text => Some(token.token_type.clone()),
+        _ => None
+    }
+}
+
+//NaN and the infinities have no JSON number, they are written as
+//{"number": "NaN"} and so on
+fn literal(json: &Json) -> Result<Atom, String> {
+    match json {
+        Json::Null => Ok(Atom::Nil),
+        Json::Bool(b) => Ok(Atom::Bool(*b)),
+        Json::Number(n) => Ok(Atom::Number(*n)),
+        Json::String(s) => Ok(Atom::String(s.clone())),
+        Json::Object(_) => match string(field(json, "number")?, "number")? {
+            "NaN" => Ok(Atom::Number(f64::NAN)),
+            "Infinity" => Ok(Atom::Number(f64::INFINITY)),
+            "-Infinity" => Ok(Atom::Number(f64::NEG_INFINITY)),
+            other => Err(format!("Invalid number {}", other))
+        },
+        _ => Err("Expected a literal value".into())
+    }
+}
+
+//spans are optional, a generated program doesn't have source lines
+fn load_span(json: &Json) -> Result<Span, String> {
+    match json.get("span") {
+        Some(span) => Ok(Span {
+            line: number(field(span, "line")?, "line")? as i32,
+            end_line: number(field(span, "end_line")?, "end_line")? as i32
+        }),
+        None => Ok(Span::default())
+    }
+}
+
+fn doc(json: &Json) -> Result<Option<String>, String> {
+    match json.get("doc") {
+        None | Some(Json::Null) => Ok(None),
+        Some(doc) => Ok(Some(string(doc, "doc")?.to_string()))
+    }
+}
+
+//names have to be identifiers the scanner would produce, not keywords or
+//anything else
+fn symbol(json: &Json) -> Result<Symbol, String> {
+    let name = string(json, "name")?;
+    match scan(name) {
+        Some(TokenType::IDENTIFIER(symbol)) => Ok(symbol),
+        _ => Err(format!("Invalid name {:?}", name))
+    }
+}
+
+fn field<'a>(json: &'a Json, key: &str) -> Result<&'a Json, String> {
+    json.get(key).ok_or_else(|| format!("Missing field {}", key))
+}
+
+fn string<'a>(json: &'a Json, what: &str) -> Result<&'a str, String> {
+    match json {
+        Json::String(s) => Ok(s),
+        _ => Err(format!("Expected {} to be a string", what))
+    }
+}
+
+fn number(json: &Json, what: &str) -> Result<f64, String> {
+    match json {
+        Json::Number(n) => Ok(*n),
+        _ => Err(format!("Expected {} to be a number", what))
+    }
+}
+
+fn array<'a>(json: &'a Json, what: &str) -> Result<&'a Vec<Json>, String> {
+    match json {
+        Json::Array(items) => Ok(items),
+        _ => Err(format!("Expected {} to be an array", what))
+    }
+}
+
+impl From<&LanguageError> for Json {
+    fn from(err: &LanguageError) -> Self {
+        Json::object(vec![
+            ("error", Json::String(err.kind().into())),
+            ("message", Json::String(err.message().into()))
+        ])
+    }
+}
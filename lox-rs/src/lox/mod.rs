@@ -2,4 +2,21 @@ pub mod scanner;
 pub mod ast;
 pub mod errors;
 pub mod parser;
+pub mod resolver;
+pub mod visit;
+pub mod fold;
 pub mod atom;
+pub mod symbol;
+pub mod document;
+pub mod json;
+pub mod sexp;
+pub mod format;
+pub mod dot;
+pub mod testing;
+pub mod engine;
+pub mod native;
+pub mod output;
+pub mod sandbox;
+pub mod host;
+pub mod session;
+pub mod trace;
@@ -0,0 +1,284 @@
+use super::atom::Atom;
+use super::errors::LanguageError;
+use super::symbol::Symbol;
+
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+//a function written in Rust and called from Lox
+pub struct Native {
+    pub name: Symbol,
+    pub arity: Arity,
+    fun: Box<dyn Fn(Vec<Atom>) -> Result<Atom, LanguageError>>
+}
+
+impl Native {
+    pub fn new(name: Symbol, arity: Arity, fun: impl Fn(Vec<Atom>) -> Result<Atom, LanguageError> + 'static) -> Self {
+        Native { name, arity, fun: Box::new(fun) }
+    }
+
+    pub fn call(&self, args: Vec<Atom>) -> Result<Atom, LanguageError> {
+        if !self.arity.accepts(args.len()) {
+            return Err(LanguageError::RuntimeError(format!("Expected {} arguments but got {}", self.arity, args.len())));
+        }
+        (self.fun)(args)
+    }
+}
+
+impl fmt::Debug for Native {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+//how many arguments a native takes, rest is set when a trailing Vec
+//parameter takes any number after the fixed ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arity {
+    pub fixed: usize,
+    pub rest: bool
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        count == self.fixed || (self.rest && count > self.fixed)
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.rest {
+            write!(f, "at least {}", self.fixed)
+        }else{
+            write!(f, "{}", self.fixed)
+        }
+    }
+}
+
+//a Rust value a native can take as an argument
+pub trait FromAtom: Sized {
+    //what a mismatched argument should have been, for the error
+    fn expected() -> String;
+
+    //the atom back when it doesn't convert
+    fn from_atom(atom: Atom) -> Result<Self, Atom>;
+
+    //set for a parameter that takes the rest of the arguments
+    const REST: bool = false;
+
+    fn take(args: &mut Args) -> Result<Self, LanguageError> {
+        let atom = args.take()?;
+        Self::from_atom(atom).map_err(|atom| args.mismatch(&Self::expected(), &atom))
+    }
+}
+
+//a Rust value a native can return
+pub trait IntoAtom {
+    fn into_atom(self) -> Atom;
+}
+
+//the arguments of one call, taken in order by each parameter
+pub struct Args {
+    name: Symbol,
+    values: std::vec::IntoIter<Atom>,
+    position: usize
+}
+
+impl Args {
+    pub fn new(name: Symbol, values: Vec<Atom>) -> Self {
+        Args { name, values: values.into_iter(), position: 0 }
+    }
+
+    pub fn take(&mut self) -> Result<Atom, LanguageError> {
+        self.position += 1;
+        self.values.next().ok_or_else(|| {
+            LanguageError::RuntimeError(format!("{}: missing argument {}", self.name, self.position))
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.len() == 0
+    }
+
+    pub fn mismatch(&self, expected: &str, found: &Atom) -> LanguageError {
+        LanguageError::RuntimeError(format!("{}: argument {} should be {} but got {}", self.name, self.position, expected, found.type_name()))
+    }
+}
+
+impl FromAtom for Atom {
+    fn expected() -> String {
+        String::from("any value")
+    }
+
+    fn from_atom(atom: Atom) -> Result<Self, Atom> {
+        Ok(atom)
+    }
+}
+
+impl FromAtom for f64 {
+    fn expected() -> String {
+        String::from("a Number")
+    }
+
+    fn from_atom(atom: Atom) -> Result<Self, Atom> {
+        match atom {
+            Atom::Number(n) => Ok(n),
+            other => Err(other)
+        }
+    }
+}
+
+impl FromAtom for bool {
+    fn expected() -> String {
+        String::from("a Bool")
+    }
+
+    fn from_atom(atom: Atom) -> Result<Self, Atom> {
+        match atom {
+            Atom::Bool(b) => Ok(b),
+            other => Err(other)
+        }
+    }
+}
+
+impl FromAtom for String {
+    fn expected() -> String {
+        String::from("a String")
+    }
+
+    fn from_atom(atom: Atom) -> Result<Self, Atom> {
+        match atom {
+            Atom::String(s) => Ok(s),
+            other => Err(other)
+        }
+    }
+}
+
+//nil is None
+impl<T: FromAtom> FromAtom for Option<T> {
+    fn expected() -> String {
+        format!("{} or nil", T::expected())
+    }
+
+    fn from_atom(atom: Atom) -> Result<Self, Atom> {
+        match atom {
+            Atom::Nil => Ok(None),
+            other => T::from_atom(other).map(Some)
+        }
+    }
+}
+
+//Lox has no lists, so a Vec is only useful as the last parameter where it
+//takes every argument left over. Registering a native with a Vec anywhere
+//else is an error. Returned Vecs become host::List objects
+impl<T: FromAtom> FromAtom for Vec<T> {
+    fn expected() -> String {
+        T::expected()
+    }
+
+    fn from_atom(atom: Atom) -> Result<Self, Atom> {
+        T::from_atom(atom).map(|value| vec![value])
+    }
+
+    const REST: bool = true;
+
+    fn take(args: &mut Args) -> Result<Self, LanguageError> {
+        let mut values = Vec::new();
+        while !args.is_empty() {
+            values.push(T::take(args)?);
+        }
+        Ok(values)
+    }
+}
+
+impl IntoAtom for Atom {
+    fn into_atom(self) -> Atom {
+        self
+    }
+}
+
+impl IntoAtom for () {
+    fn into_atom(self) -> Atom {
+        Atom::Nil
+    }
+}
+
+impl IntoAtom for f64 {
+    fn into_atom(self) -> Atom {
+        Atom::Number(self)
+    }
+}
+
+impl IntoAtom for bool {
+    fn into_atom(self) -> Atom {
+        Atom::Bool(self)
+    }
+}
+
+impl IntoAtom for String {
+    fn into_atom(self) -> Atom {
+        Atom::String(self)
+    }
+}
+
+impl IntoAtom for &str {
+    fn into_atom(self) -> Atom {
+        Atom::String(self.to_string())
+    }
+}
+
+impl<T: IntoAtom> IntoAtom for Option<T> {
+    fn into_atom(self) -> Atom {
+        self.map_or(Atom::Nil, T::into_atom)
+    }
+}
+
+//a Rust closure or fn that can be registered as a native. Params is the
+//tuple of its parameter types, it only tells the impls apart. Fails when
+//a parameter before the last takes the rest of the arguments
+pub trait IntoNative<Params> {
+    fn into_native(self, name: Symbol) -> Result<Native, LanguageError>;
+}
+
+macro_rules! into_native {
+    ($($param:ident $arg:ident),*) => {
+        impl<F, R, $($param),*> IntoNative<($($param,)*)> for F
+        where F: Fn($($param),*) -> R + 'static, R: IntoAtom, $($param: FromAtom),* {
+            #[allow(unused_mut, unused_variables)]
+            fn into_native(self, name: Symbol) -> Result<Native, LanguageError> {
+                let params: &[bool] = &[$($param::REST),*];
+                if let Some(position) = params.iter().position(|rest| *rest).filter(|position| position + 1 < params.len()) {
+                    return Err(LanguageError::TypeError(format!("{}: only the last parameter can take the rest of the arguments, not parameter {}", name, position + 1)));
+                }
+                let arity = Arity { fixed: params.iter().filter(|rest| !**rest).count(), rest: params.contains(&true) };
+                Ok(Native::new(name.clone(), arity, move |values| {
+                    let mut args = Args::new(name.clone(), values);
+                    $(let $arg = $param::take(&mut args)?;)*
+                    Ok(self($($arg),*).into_atom())
+                }))
+            }
+        }
+    };
+}
+
+into_native!();
+into_native!(A a);
+into_native!(A a, B b);
+into_native!(A a, B b, C c);
+into_native!(A a, B b, C c, D d);
+into_native!(A a, B b, C c, D d, E e);
+
+//seconds since the Unix epoch
+pub fn clock() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0.0, |time| time.as_secs_f64())
+}
+
+//str(value), the value as print would show it
+pub fn stringify(value: Atom) -> String {
+    value.to_string()
+}
+
+//typeof(value), the name of the value's type
+pub fn type_of(value: Atom) -> &'static str {
+    value.type_name()
+}
@@ -0,0 +1,70 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+//an in-memory stream for capturing what a program prints. Clones share
+//the same bytes, so one can be handed to the interpreter and the other
+//kept to read from
+#[derive(Debug, Clone, Default)]
+pub struct Buffer {
+    bytes: Rc<RefCell<Vec<u8>>>
+}
+
+impl Buffer {
+    pub fn new() -> Self {
+        Buffer::default()
+    }
+
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.bytes.borrow()).into_owned()
+    }
+
+    //the contents so far, leaving the buffer empty
+    pub fn take(&self) -> String {
+        let bytes = std::mem::take(&mut *self.bytes.borrow_mut());
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+//a stream that can be replaced while others keep writing to it. Clones
+//write to whatever the stream is at the time, the interpreter's stderr is
+//one so a Tracer handed a clone follows set_stderr
+#[derive(Clone)]
+pub struct Shared {
+    inner: Rc<RefCell<Box<dyn Write>>>
+}
+
+impl Shared {
+    pub fn new(out: impl Write + 'static) -> Self {
+        Shared { inner: Rc::new(RefCell::new(Box::new(out))) }
+    }
+
+    pub fn replace(&self, out: impl Write + 'static) {
+        *self.inner.borrow_mut() = Box::new(out);
+    }
+
+    fn borrow_mut(&self) -> io::Result<std::cell::RefMut<'_, Box<dyn Write>>> {
+        self.inner.try_borrow_mut().map_err(|_| io::Error::other("stream is already being written"))
+    }
+}
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.borrow_mut()?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.borrow_mut()?.flush()
+    }
+}
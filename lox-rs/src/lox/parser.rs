@@ -1,184 +1,576 @@
+use std::collections::HashMap;
 use std::mem::discriminant;
+use std::ops::Range;
 use super::scanner::{TokenType, Token};
 use super::atom::Atom;
-use super::ast::{Expr, Stmt};
+use super::ast::{Ast, Expr, ExprId, FunctionDecl, FunctionId, Span, Stmt, StmtId};
 use super::errors::LanguageError;
+use super::symbol::Symbol;
+
+//how deep expressions and blocks may nest by default. Every pass over the
+//tree recurses as deep as the tree goes, so the height of every tree the
+//parser builds is held to this too, not just its own recursion. Otherwise
+//a long chain like `1 + 1 + ...` or `f()()...`, parsed in a loop, would
+//overflow the stack in the passes after it
+pub const MAX_DEPTH: usize = 256;
+
+//binding power of operators, from loosest to tightest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precedence {
+    None,
+    //lists of expressions (arguments and the like) are parsed above this
+    //level so their commas aren't taken as the comma operator
+    Comma,
+    Assignment,
+    Conditional,
+    Equality,
+    Comparison,
+    Term,
+    Factor,
+    Unary,
+    Exponent,
+    Call,
+    Primary
+}
+
+impl Precedence {
+    pub fn next(self) -> Precedence {
+        match self {
+            Precedence::None => Precedence::Comma,
+            Precedence::Comma => Precedence::Assignment,
+            Precedence::Assignment => Precedence::Conditional,
+            Precedence::Conditional => Precedence::Equality,
+            Precedence::Equality => Precedence::Comparison,
+            Precedence::Comparison => Precedence::Term,
+            Precedence::Term => Precedence::Factor,
+            Precedence::Factor => Precedence::Unary,
+            Precedence::Unary => Precedence::Exponent,
+            Precedence::Exponent => Precedence::Call,
+            Precedence::Call | Precedence::Primary => Precedence::Primary
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assoc {
+    Left,
+    Right
+}
+
+//how tight an infix operator binds and which way it groups, for writing a
+//tree back out as source
+pub fn binding(ttype: &TokenType) -> (Precedence, Assoc) {
+    let rule = Parser::rule(ttype);
+    (rule.precedence, rule.assoc)
+}
+
+//prefix handlers consume their own token, infix and postfix handlers get
+//the expression parsed so far and consume the operator
+type PrefixFn<'a> = fn(&mut Parser<'a>) -> ExprId;
+type InfixFn<'a> = fn(&mut Parser<'a>, ExprId) -> ExprId;
+
+struct ParseRule<'a> {
+    prefix: Option<PrefixFn<'a>>,
+    infix: Option<InfixFn<'a>>,
+    postfix: Option<InfixFn<'a>>,
+    precedence: Precedence,
+    assoc: Assoc
+}
 
 pub struct Parser<'a> {
     tokens: &'a Vec<Token>,
+    //nodes are added here as they are parsed
+    ast: &'a mut Ast,
     current: usize,
-    errs: Vec<LanguageError>
+    errs: Vec<LanguageError>,
+    //how many function bodies we are in, return is only allowed inside one
+    function_depth: usize,
+    //how many expressions and blocks we are in
+    depth: usize,
+    max_depth: usize,
+    //how many errors there were once the limit was hit, the rest are just
+    //the fallout of giving up
+    too_deep: Option<usize>,
+    //height of the tree under each node added so far
+    expr_heights: HashMap<ExprId, usize>,
+    stmt_heights: HashMap<StmtId, usize>
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(tokens: &'a Vec<Token>) -> Self {
-        Self { tokens, current: 0, errs: Vec::new()}
+    pub fn new(tokens: &'a Vec<Token>, ast: &'a mut Ast) -> Self {
+        let mut parser = Self { tokens, ast, current: 0, errs: Vec::new(), function_depth: 0, depth: 0, max_depth: MAX_DEPTH, too_deep: None, expr_heights: HashMap::new(), stmt_heights: HashMap::new() };
+        parser.current = parser.skip_docs(0);
+        parser
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Stmt>,String> {
-        let mut statements: Vec<Stmt> = Vec::new();
-        while self.current < self.tokens.len() - 1 {
-           statements.push(self.decl());
-        }
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn parse(&mut self) -> Result<Vec<StmtId>,String> {
+        let statements: Vec<StmtId> = self.declarations()
+                                        .into_iter()
+                                        .map(|(stmt, _, _)| stmt)
+                                        .collect();
         
-        if self.errs.len() == 0 {
+        if self.errs.is_empty() {
             Ok(statements)
         }else{
            Err("Error for some reason".into())
         }
     }
 
-    fn stmt(&mut self) -> Stmt {
+    //parses the top level declarations, keeping the range of tokens each
+    //one was parsed from and the errors found while parsing it
+    pub fn declarations(&mut self) -> Vec<(StmtId, Range<usize>, Vec<LanguageError>)> {
+        let mut decls = Vec::new();
+        while self.current < self.tokens.len() - 1 {
+           let start = self.doc_start();
+           let errs = self.errs.len();
+           let doc = self.doc_comment();
+           let before = self.current;
+           let stmt = self.decl(doc);
+           //a stray `;` or `}` nothing was parsed from is skipped
+           if self.current == before {
+               self.advance();
+           }
+           if let Some(len) = self.too_deep {
+               self.errs.truncate(len);
+           }
+           //doc comments after the statement belong to the next one
+           decls.push((stmt, start..self.doc_start(), self.errs[errs..].to_vec()));
+        }
+
+        decls
+    }
+
+    pub fn errors(&self) -> &Vec<LanguageError> {
+        &self.errs
+    }
+
+    fn stmt(&mut self) -> StmtId {
         if self.tmatch(&[TokenType::PRINT]){
             self.print_stmt()
+        }else if self.tmatch(&[TokenType::LEFTBRACE]) {
+            let line = self.prev().line;
+            let stmts = self.block();
+            self.add_stmt(line, Stmt::Block(stmts))
+        }else if self.tmatch(&[TokenType::RETURN]) {
+            self.return_stmt()
         }else{
             self.expr_stmt()
         }
     }
 
-    fn decl(&mut self) -> Stmt {
+    fn decl(&mut self, doc: Option<String>) -> StmtId {
         if self.tmatch(&[TokenType::VAR]) {
-            self.var_decl()
+            self.var_decl(doc)
+        }else if self.check(&TokenType::FUN) && self.check_next(&TokenType::IDENTIFIER(Symbol::intern(""))) {
+            self.advance();
+            self.fun_decl(doc)
         }else{
             self.stmt()
         }
     }
 
-    fn var_decl(&mut self) -> Stmt {
+    fn fun_decl(&mut self, doc: Option<String>) -> StmtId {
+        let line = self.prev().line;
+        let name = self.identifier();
+        if let Err(err) = self.consume(&TokenType::LEFTPAREN) {
+            self.error(err);
+        }
+        let params = self.params();
+        let body = self.function_body();
+
+        let decl = self.ast.add_function(FunctionDecl { name: Some(name), params, body });
+        self.add_stmt(line, Stmt::Function(decl, doc))
+    }
+
+    //everything after the opening brace of a block
+    fn block(&mut self) -> Vec<StmtId> {
+        let mut stmts: Vec<StmtId> = Vec::new();
+        if !self.enter() {
+            return stmts;
+        }
+
+        while !self.check(&TokenType::RIGHTBRACE) && !self.check(&TokenType::EOF) {
+            let doc = self.doc_comment();
+            stmts.push(self.decl(doc));
+        }
+
+        if let Err(err) = self.consume(&TokenType::RIGHTBRACE) {
+            self.error(err);
+        }
+        self.depth -= 1;
+        stmts
+    }
+
+    fn function_body(&mut self) -> Vec<StmtId> {
+        if let Err(err) = self.consume(&TokenType::LEFTBRACE) {
+            self.error(err);
+        }
+
+        self.function_depth += 1;
+        let body = self.block();
+        self.function_depth -= 1;
+        body
+    }
+
+    //parameter names up to and including the closing paren
+    fn params(&mut self) -> Vec<Symbol> {
+        let mut params: Vec<Symbol> = Vec::new();
+        if !self.check(&TokenType::RIGHTPAREN) {
+            loop {
+                params.push(self.identifier());
+                if !self.tmatch(&[TokenType::COMMA]) {
+                    break;
+                }
+            }
+        }
+
+        if let Err(err) = self.consume(&TokenType::RIGHTPAREN) {
+            self.error(err);
+        }
+        params
+    }
+
+    fn identifier(&mut self) -> Symbol {
+        if let TokenType::IDENTIFIER(name) = self.peek().token_type {
+            self.advance();
+            name
+        }else{
+            self.error("Expected Identifier".into());
+            Symbol::intern("")
+        }
+    }
+
+    fn return_stmt(&mut self) -> StmtId {
+        let line = self.prev().line;
+        if self.function_depth == 0 {
+            self.errs.push(LanguageError::ParserError("Can't return from top-level code".into()));
+        }
+
+        let val: ExprId = if self.check(&TokenType::SEMICOLON) {
+            self.add_expr(line, Expr::Literal(Atom::Nil))
+        }else{
+            self.expr()
+        };
+
+        if let Err(err) = self.consume(&TokenType::SEMICOLON) {
+            self.error(err);
+        }
+        self.add_stmt(line, Stmt::Return(val))
+    }
+
+    //the `///` lines right before the current token, joined into a single
+    //doc comment. Everywhere else the parser steps over doc comments like
+    //any other comment, only a declaration picks up the ones before it
+    fn doc_comment(&self) -> Option<String> {
+        let lines: Vec<String> = self.tokens[self.doc_start()..self.current]
+            .iter()
+            .filter_map(|token| match &token.token_type {
+                TokenType::DOC(line) => Some(line.clone()),
+                _ => None
+            })
+            .collect();
+
+        if lines.is_empty() {
+            None
+        }else{
+            Some(lines.join("\n"))
+        }
+    }
+
+    //where the doc comments before the current token start
+    fn doc_start(&self) -> usize {
+        let mut start = self.current;
+        while start > 0 && matches!(self.tokens[start - 1].token_type, TokenType::DOC(_)) {
+            start -= 1;
+        }
+        start
+    }
+
+    //the first token from i on that isn't a doc comment
+    fn skip_docs(&self, mut i: usize) -> usize {
+        while matches!(self.tokens.get(i).map(|t| &t.token_type), Some(TokenType::DOC(_))) {
+            i += 1;
+        }
+        i
+    }
+
+    fn var_decl(&mut self, doc: Option<String>) -> StmtId {
+        let line = self.prev().line;
         let lval = if let TokenType::IDENTIFIER(lval) = self.peek().token_type {
             lval
         }else{
-            self.errs.push(LanguageError::ParserError("Expected Identifier".into()));
-            "".into() 
+            self.error("Expected Identifier".into());
+            Symbol::intern("")
         };
         
-        let mut rval: Expr = Expr::Literal(Atom::Nil);
-
         self.advance();
-        if self.tmatch(&[TokenType::EQUAL]) {
-            rval = self.expr();
+        let rval: ExprId = if self.tmatch(&[TokenType::EQUAL]) {
+            self.expr()
+        }else{
+            self.add_expr(line, Expr::Literal(Atom::Nil))
         };
 
         match self.consume(&TokenType::SEMICOLON) {
             Ok(_) => {},
-            Err(err) => self.errs.push(LanguageError::ParserError(err))
+            Err(err) => self.error(err)
         };
 
-        Stmt::Var(Box::new(lval), Box::new(rval))
+        self.add_stmt(line, Stmt::Var(lval, rval, doc))
     }
 
-    fn expr_stmt(&mut self) -> Stmt {
-        let expr: Expr = self.expr();
+    fn expr_stmt(&mut self) -> StmtId {
+        let line = self.peek().line;
+        let expr: ExprId = self.expr();
         match self.consume(&TokenType::SEMICOLON) {
             Ok(_) => {},
-            Err(err) => self.errs.push(LanguageError::ParserError(err))
+            Err(err) => self.error(err)
         };
-        Stmt::Expr(Box::new(expr))
+        self.add_stmt(line, Stmt::Expr(expr))
     }
 
-    fn print_stmt(&mut self) -> Stmt {
-        let val: Expr = self.expr();
+    fn print_stmt(&mut self) -> StmtId {
+        let line = self.prev().line;
+        let val: ExprId = self.expr();
         match self.consume(&TokenType::SEMICOLON) {
             Ok(_) => {},
-            Err(err) => self.errs.push(LanguageError::ParserError(err))
+            Err(err) => self.error(err)
         };
         
-        Stmt::Print(Box::new(val))
+        self.add_stmt(line, Stmt::Print(val))
     }
 
-    fn expr(&mut self) -> Expr {
-        self.equality()
+    fn expr(&mut self) -> ExprId {
+        self.parse_precedence(Precedence::Comma)
     }
 
-    fn equality(&mut self) -> Expr {
-        let mut expr: Expr = self.comparison();
-        while 
-            self.tmatch(&[TokenType::BANGEQUAL, TokenType::EQUALEQUAL]){
-                let op: Token = self.prev();
-                let rhs: Expr = self.comparison();
+    //parses any expression whose operators bind at least as tight as min
+    fn parse_precedence(&mut self, min: Precedence) -> ExprId {
+        if !self.enter() {
+            let line = self.peek().line;
+            return self.add_expr(line, Expr::Literal(Atom::Nil));
+        }
 
-                expr = Expr::Binary(
-                                Box::new(expr),
-                                Box::new(op),
-                                Box::new(rhs));
+        let prefix = Self::rule(&self.peek().token_type).prefix.unwrap_or(Self::primary);
+        let mut expr: ExprId = prefix(self);
+
+        loop {
+            let rule = Self::rule(&self.peek().token_type);
+            if rule.precedence < min {
+                break;
+            }
+
+            match rule.infix.or(rule.postfix) {
+                Some(handler) => expr = handler(self, expr),
+                None => break
             }
+        }
 
+        self.depth -= 1;
         expr
+    }
+
+    //one level deeper, past the limit parsing stops at the first error
+    //and skips to the end rather than recursing any further
+    fn enter(&mut self) -> bool {
+        if self.depth < self.max_depth {
+            self.depth += 1;
+            return true;
+        }
 
+        self.too_deep();
+        false
     }
 
-    fn comparison(&mut self) -> Expr {
-        let mut expr: Expr = self.term();
+    fn too_deep(&mut self) {
+        if self.too_deep.is_none() {
+            self.errs.push(LanguageError::ParserError(format!("Nesting deeper than {} levels", self.max_depth)));
+            self.too_deep = Some(self.errs.len());
+        }
+        self.current = self.tokens.len() - 1;
+    }
 
-        while 
-            self.tmatch(&[TokenType::GREATER, 
-                          TokenType::GREATEREQUAL,
-                          TokenType::LESS,
-                          TokenType::LESSEQUAL]) {
-                let op: Token = self.prev();
-                let rhs: Expr = self.term();
+    //the operator table, a new operator only needs a row here
+    fn rule(ttype: &TokenType) -> ParseRule<'a> {
+        let (prefix, infix, postfix, precedence, assoc): (Option<PrefixFn<'a>>, Option<InfixFn<'a>>, Option<InfixFn<'a>>, Precedence, Assoc) = match ttype {
+            TokenType::LEFTPAREN => (Some(Self::paren), None, Some(Self::call), Precedence::Call, Assoc::Left),
+            TokenType::DOT => (None, None, Some(Self::get), Precedence::Call, Assoc::Left),
+            TokenType::FUN => (Some(Self::lambda), None, None, Precedence::None, Assoc::Left),
+            TokenType::COMMA => (None, Some(Self::binary), None, Precedence::Comma, Assoc::Left),
+            TokenType::EQUAL |
+            TokenType::PLUSEQUAL |
+            TokenType::MINUSEQUAL |
+            TokenType::STAREQUAL |
+            TokenType::SLASHEQUAL |
+            TokenType::PERCENTEQUAL => (None, Some(Self::assignment), None, Precedence::Assignment, Assoc::Right),
+            TokenType::QUESTION => (None, Some(Self::conditional), None, Precedence::Conditional, Assoc::Right),
+            TokenType::MINUS => (Some(Self::unary), Some(Self::binary), None, Precedence::Term, Assoc::Left),
+            TokenType::PLUS => (None, Some(Self::binary), None, Precedence::Term, Assoc::Left),
+            TokenType::SLASH |
+            TokenType::STAR |
+            TokenType::PERCENT |
+            TokenType::TILDESLASH => (None, Some(Self::binary), None, Precedence::Factor, Assoc::Left),
+            TokenType::STARSTAR => (None, Some(Self::binary), None, Precedence::Exponent, Assoc::Right),
+            TokenType::BANG => (Some(Self::unary), None, None, Precedence::None, Assoc::Left),
+            TokenType::BANGEQUAL |
+            TokenType::EQUALEQUAL => (None, Some(Self::binary), None, Precedence::Equality, Assoc::Left),
+            TokenType::GREATER |
+            TokenType::GREATEREQUAL |
+            TokenType::LESS |
+            TokenType::LESSEQUAL => (None, Some(Self::binary), None, Precedence::Comparison, Assoc::Left),
+            _ => (None, None, None, Precedence::None, Assoc::Left)
+        };
 
-                expr = Expr::Binary(
-                                Box::new(expr),
-                                Box::new(op),
-                                Box::new(rhs));
-            }
+        ParseRule { prefix, infix, postfix, precedence, assoc }
+    }
 
-        expr
+    fn binary(&mut self, lhs: ExprId) -> ExprId {
+        self.advance();
+        let op: Token = self.prev();
+        let rule = Self::rule(&op.token_type);
+        let rhs: ExprId = match rule.assoc {
+            Assoc::Left => self.parse_precedence(rule.precedence.next()),
+            Assoc::Right => self.parse_precedence(rule.precedence)
+        };
+
+        let line = self.ast.expr_span(lhs).line;
+        self.add_expr(line, Expr::Binary(lhs, op, rhs))
     }
 
-    fn term(&mut self) -> Expr {
-        let mut expr: Expr = self.factor();
+    //anonymous function, `fun (a, b) { ... }`
+    fn lambda(&mut self) -> ExprId {
+        let line = self.peek().line;
+        self.advance();
+        if let Err(err) = self.consume(&TokenType::LEFTPAREN) {
+            self.error(err);
+        }
+        let params = self.params();
+        let body = self.function_body();
 
-        while
-            self.tmatch(&[TokenType::MINUS,
-                          TokenType::PLUS]) {
-                let op: Token = self.prev();
-                let rhs: Expr = self.factor();
+        let decl = self.ast.add_function(FunctionDecl { name: None, params, body });
+        self.add_expr(line, Expr::Lambda(decl))
+    }
 
-                expr = Expr::Binary(
-                                Box::new(expr),
-                                Box::new(op),
-                                Box::new(rhs));
-            }
+    //either a grouping or the parameter list of an arrow lambda,
+    //`(a, b) => a + b` or `(a, b) => { ... }`
+    fn paren(&mut self) -> ExprId {
+        if !self.is_arrow_lambda() {
+            return self.primary();
+        }
 
-        expr
+        let line = self.peek().line;
+        self.advance();
+        let params = self.params();
+        self.advance();
+
+        let body = if self.check(&TokenType::LEFTBRACE) {
+            self.function_body()
+        }else{
+            let body_line = self.peek().line;
+            let value = self.parse_precedence(Precedence::Assignment);
+            vec![self.add_stmt(body_line, Stmt::Return(value))]
+        };
+
+        let decl = self.ast.add_function(FunctionDecl { name: None, params, body });
+        self.add_expr(line, Expr::Lambda(decl))
     }
 
-    fn factor(&mut self) -> Expr {
-        let mut expr: Expr = self.unary();
-        while 
-            self.tmatch(&[TokenType::SLASH, TokenType::STAR]) {
-                let op: Token = self.prev();
-                let rhs: Expr = self.unary();
+    fn is_arrow_lambda(&self) -> bool {
+        let mut i = self.skip_docs(self.current + 1);
+        loop {
+            match self.tokens.get(i).map(|t| &t.token_type) {
+                Some(TokenType::IDENTIFIER(_)) | Some(TokenType::COMMA) => i = self.skip_docs(i + 1),
+                Some(TokenType::RIGHTPAREN) => {
+                    return matches!(self.tokens.get(self.skip_docs(i + 1)).map(|t| &t.token_type), Some(TokenType::ARROW));
+                },
+                _ => return false
+            }
+        }
+    }
 
-                expr = Expr::Binary(
-                                Box::new(expr),
-                                Box::new(op),
-                                Box::new(rhs));
+    //arguments are parsed above the comma operator so commas separate them
+    fn call(&mut self, callee: ExprId) -> ExprId {
+        self.advance();
+        let mut args: Vec<ExprId> = Vec::new();
+        if !self.check(&TokenType::RIGHTPAREN) {
+            loop {
+                args.push(self.parse_precedence(Precedence::Assignment));
+                if !self.tmatch(&[TokenType::COMMA]) {
+                    break;
+                }
             }
+        }
 
-        expr
+        if let Err(err) = self.consume(&TokenType::RIGHTPAREN) {
+            self.error(err);
+        }
+
+        let line = self.ast.expr_span(callee).line;
+        let paren = self.prev();
+        self.add_expr(line, Expr::Call(callee, paren, args))
+    }
+
+    //property access, `object.name`
+    fn get(&mut self, object: ExprId) -> ExprId {
+        self.advance();
+        let name = self.identifier();
+        let line = self.ast.expr_span(object).line;
+        self.add_expr(line, Expr::Get(object, name))
     }
 
-    fn unary(&mut self) -> Expr {
-        if self.tmatch(&[TokenType::BANG, TokenType::MINUS]) {
-            let op: Token = self.prev();
-            let rhs: Expr = self.unary();
+    //plain and compound assignment, only variables and properties can be
+    //assigned to
+    fn assignment(&mut self, target: ExprId) -> ExprId {
+        self.advance();
+        let op: Token = self.prev();
+        let value: ExprId = self.parse_precedence(Precedence::Assignment);
+
+        let line = self.ast.expr_span(target).line;
+        match self.ast.expr(target).clone() {
+            Expr::Var(name) => self.add_expr(line, Expr::Assign(name, op, value)),
+            Expr::Get(object, name) => self.add_expr(line, Expr::Set(object, name, op, value)),
+            _ => {
+                self.errs.push(LanguageError::ParserError("Invalid assignment target".into()));
+                value
+            }
+        }
+    }
 
-            return Expr::Unary(
-                        Box::new(op),
-                        Box::new(rhs));
+    //cond ? then : else, the middle is parsed as if it were parenthesized
+    fn conditional(&mut self, cond: ExprId) -> ExprId {
+        self.advance();
+        let then: ExprId = self.expr();
+        if let Err(err) = self.consume(&TokenType::COLON) {
+            self.error(err);
         }
+        let otherwise: ExprId = self.parse_precedence(Precedence::Conditional);
 
-        return self.primary();
+        let line = self.ast.expr_span(cond).line;
+        self.add_expr(line, Expr::Conditional(cond, then, otherwise))
     }
 
-    fn primary(&mut self) -> Expr {
-        let token = self.peek();
+    fn unary(&mut self) -> ExprId {
         self.advance();
+        let op: Token = self.prev();
+        let rhs: ExprId = self.parse_precedence(Precedence::Unary);
+
+        let line = op.line;
+        self.add_expr(line, Expr::Unary(op, rhs))
+    }
+
+    //a token that can't start an expression is an error. One that ends a
+    //statement or block is left for it, so parsing picks up from there
+    fn primary(&mut self) -> ExprId {
+        let token = self.peek();
+        if !matches!(token.token_type, TokenType::SEMICOLON | TokenType::RIGHTBRACE | TokenType::EOF) {
+            self.advance();
+        }
 
-        match token.token_type {
+        let expr = match token.token_type {
             TokenType::FALSE => Expr::Literal(Atom::Bool(false)),
             TokenType::TRUE => Expr::Literal(Atom::Bool(true)),
             TokenType::NIL => Expr::Literal(Atom::Nil),
@@ -188,12 +580,82 @@ impl<'a> Parser<'a> {
                 let expr = self.expr();
                 match self.consume(&TokenType::RIGHTPAREN) {
                     Ok(_) => {},
-                    Err(err) => self.errs.push(LanguageError::ParserError(err))
+                    Err(err) => self.error(err)
                 }
-                Expr::Grouping(Box::new(expr))
+                Expr::Grouping(expr)
+            },
+            TokenType::IDENTIFIER(var) => Expr::Var(var),
+            TokenType::EOF => {
+                self.errs.push(LanguageError::IncompleteInput("Expected expression, found EOF".into()));
+                Expr::Literal(Atom::Nil)
             },
-            TokenType::IDENTIFIER(var) => Expr::Var(Box::new(var)),
-            _ => unreachable!()
+            t => {
+                self.errs.push(LanguageError::ParserError(format!("Expected expression, found {:?}", t)));
+                Expr::Literal(Atom::Nil)
+            }
+        };
+        self.add_expr(token.line, expr)
+    }
+
+    //a node spanning from line to the last token consumed. A node that
+    //makes the tree too deep stops the parse like nesting too deep does
+    fn add_expr(&mut self, line: i32, expr: Expr) -> ExprId {
+        let height = 1 + match &expr {
+            Expr::Assign(_, _, value) => self.expr_height(*value),
+            Expr::Binary(lhs, _, rhs) => self.expr_height(*lhs).max(self.expr_height(*rhs)),
+            Expr::Call(callee, _, args) => args.iter().fold(self.expr_height(*callee), |height, arg| height.max(self.expr_height(*arg))),
+            Expr::Get(object, _) => self.expr_height(*object),
+            Expr::Set(object, _, _, value) => self.expr_height(*object).max(self.expr_height(*value)),
+            Expr::Grouping(inner) | Expr::Unary(_, inner) => self.expr_height(*inner),
+            Expr::Lambda(decl) => self.function_height(*decl),
+            Expr::Conditional(cond, then, otherwise) => self.expr_height(*cond).max(self.expr_height(*then)).max(self.expr_height(*otherwise)),
+            Expr::Literal(_) | Expr::Var(_) => 0
+        };
+        if height > self.max_depth {
+            self.too_deep();
+        }
+
+        let span = Span { line, end_line: self.prev().line };
+        let id = self.ast.add_expr(expr, span);
+        self.expr_heights.insert(id, height);
+        id
+    }
+
+    fn add_stmt(&mut self, line: i32, stmt: Stmt) -> StmtId {
+        let height = 1 + match &stmt {
+            Stmt::Block(stmts) => self.stmts_height(stmts),
+            Stmt::Expr(expr) | Stmt::Print(expr) | Stmt::Return(expr) | Stmt::Var(_, expr, _) => self.expr_height(*expr),
+            Stmt::Function(decl, _) => self.function_height(*decl)
+        };
+        if height > self.max_depth {
+            self.too_deep();
+        }
+
+        let span = Span { line, end_line: self.prev().line };
+        let id = self.ast.add_stmt(stmt, span);
+        self.stmt_heights.insert(id, height);
+        id
+    }
+
+    fn expr_height(&self, id: ExprId) -> usize {
+        self.expr_heights.get(&id).copied().unwrap_or(0)
+    }
+
+    fn stmts_height(&self, stmts: &[StmtId]) -> usize {
+        stmts.iter().map(|stmt| self.stmt_heights.get(stmt).copied().unwrap_or(0)).max().unwrap_or(0)
+    }
+
+    fn function_height(&self, decl: FunctionId) -> usize {
+        1 + self.stmts_height(&self.ast.function(decl).body)
+    }
+
+    //running out of tokens means the input stopped short rather than
+    //being wrong, more of it could still make it parse
+    fn error(&mut self, msg: String) {
+        if self.check(&TokenType::EOF) {
+            self.errs.push(LanguageError::IncompleteInput(msg));
+        }else{
+            self.errs.push(LanguageError::ParserError(msg));
         }
     }
 
@@ -206,15 +668,21 @@ impl<'a> Parser<'a> {
     }
 
     fn advance(&mut self) -> Token {
-        if self.current < self.tokens.len() {
-            self.current += 1;
+        //never move past EOF
+        if self.current < self.tokens.len() - 1 {
+            self.current = self.skip_docs(self.current + 1);
         }
 
         self.peek()
     }
 
     fn prev(&self) -> Token {
-        self.tokens[self.current - 1].clone()
+        self.tokens[..self.current]
+            .iter()
+            .rev()
+            .find(|t| !matches!(t.token_type, TokenType::DOC(_)))
+            .unwrap_or(&self.tokens[self.current])
+            .clone()
     }
 
     fn peek(&self) -> Token {
@@ -230,6 +698,13 @@ impl<'a> Parser<'a> {
 
     }
 
+    fn check_next(&self, ttype: &TokenType) -> bool {
+        match self.tokens.get(self.skip_docs(self.current + 1)) {
+            Some(token) => discriminant(&token.token_type) == discriminant(ttype),
+            None => false
+        }
+    }
+
     fn tmatch (&mut self, ttypes: &[TokenType]) -> bool {
         for ttype in ttypes {
             if self.check(ttype) {
@@ -239,6 +714,6 @@ impl<'a> Parser<'a> {
             }
         }
 
-        return false;
+        false
     }
 }
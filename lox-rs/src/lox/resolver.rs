@@ -0,0 +1,106 @@
+use super::ast::{Ast, ExprId, FunctionId, StmtId};
+use super::errors::LanguageError;
+use super::scanner::Token;
+use super::symbol::Symbol;
+use super::visit::{self, Visitor};
+
+use std::collections::HashMap;
+
+//works out how many scopes out each local variable is declared, so the
+//interpreter can go straight to the right environment. Globals are left
+//alone and looked up dynamically
+#[derive(Default)]
+pub struct Resolver {
+    //a variable is false while its initializer is being resolved
+    scopes: Vec<HashMap<Symbol, bool>>,
+    locals: Vec<(ExprId, usize)>,
+    errs: Vec<LanguageError>
+}
+
+impl Resolver {
+    pub fn resolve(mut self, ast: &Ast, stmts: &[StmtId]) -> Result<Vec<(ExprId, usize)>, Vec<LanguageError>> {
+        for stmt in stmts {
+            self.visit_stmt(ast, *stmt);
+        }
+
+        if self.errs.is_empty() {
+            Ok(self.locals)
+        }else{
+            Err(self.errs)
+        }
+    }
+
+    fn declare(&mut self, name: Symbol) {
+        if let Some(scope) = self.scopes.last_mut() {
+            if scope.insert(name.clone(), false).is_some() {
+                self.errs.push(LanguageError::ResolveError(format!("Already a variable named {} in this scope", name)));
+            }
+        }
+    }
+
+    fn define(&mut self, name: Symbol) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name, true);
+        }
+    }
+
+    fn resolve_local(&mut self, expr: ExprId, name: &Symbol) {
+        let depth = self.scopes.iter().rev().position(|scope| scope.contains_key(name));
+        if let Some(depth) = depth {
+            self.locals.push((expr, depth));
+        }
+    }
+}
+
+//resolves the statements and records the result in the Ast
+pub fn resolve(ast: &mut Ast, stmts: &[StmtId]) -> Result<(), Vec<LanguageError>> {
+    for (expr, depth) in Resolver::default().resolve(ast, stmts)? {
+        ast.resolve_local(expr, depth);
+    }
+
+    Ok(())
+}
+
+impl<'ast> Visitor<'ast> for Resolver {
+    fn visit_block_stmt(&mut self, ast: &'ast Ast, _id: StmtId, stmts: &'ast [StmtId]) {
+        self.scopes.push(HashMap::new());
+        visit::walk_block_stmt(self, ast, stmts);
+        self.scopes.pop();
+    }
+
+    fn visit_function_stmt(&mut self, ast: &'ast Ast, _id: StmtId, decl: FunctionId, _doc: Option<&'ast str>) {
+        if let Some(name) = &ast.function(decl).name {
+            self.declare(name.clone());
+            self.define(name.clone());
+        }
+        self.visit_function(ast, decl);
+    }
+
+    fn visit_var_stmt(&mut self, ast: &'ast Ast, _id: StmtId, name: &'ast Symbol, init: ExprId, _doc: Option<&'ast str>) {
+        self.declare(name.clone());
+        self.visit_expr(ast, init);
+        self.define(name.clone());
+    }
+
+    fn visit_function(&mut self, ast: &'ast Ast, id: FunctionId) {
+        self.scopes.push(HashMap::new());
+        for param in ast.function(id).params.iter() {
+            self.declare(param.clone());
+            self.define(param.clone());
+        }
+        visit::walk_function(self, ast, id);
+        self.scopes.pop();
+    }
+
+    fn visit_assign(&mut self, ast: &'ast Ast, id: ExprId, name: &'ast Symbol, _op: &'ast Token, value: ExprId) {
+        self.visit_expr(ast, value);
+        self.resolve_local(id, name);
+    }
+
+    fn visit_var(&mut self, _ast: &'ast Ast, id: ExprId, name: &'ast Symbol) {
+        if self.scopes.last().and_then(|scope| scope.get(name)) == Some(&false) {
+            self.errs.push(LanguageError::ResolveError(format!("Can't read local variable {} in its own initializer", name)));
+        }
+        self.resolve_local(id, name);
+    }
+}
@@ -0,0 +1,106 @@
+use super::errors::LanguageError;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+//how often the clock is read, in steps
+const CLOCK_INTERVAL: u64 = 256;
+
+//what a single run may use, None is unlimited. A run is one interpret or
+//one call from the host
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    //expressions and blocks evaluated
+    pub max_steps: Option<u64>,
+    pub timeout: Option<Duration>,
+    //bytes of strings and functions the script creates. They are counted
+    //as they are made and never given back, so this caps the total rather
+    //than what is alive at once. Identifier names aren't counted, they are
+    //kept for the source the host handed over and freed along with it
+    pub max_memory: Option<usize>,
+    //Lox functions that may be running at once
+    pub max_call_depth: Option<usize>
+}
+
+//stops a running script from any thread. Clones share the flag. Every
+//run clears it when it starts, so a cancel only ever stops the run in
+//progress, never the one after it
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle {
+    cancelled: Arc<AtomicBool>
+}
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+//the limits of an interpreter and what the current run has used of them
+#[derive(Debug, Default)]
+pub struct Sandbox {
+    pub limits: Limits,
+    cancel: CancelHandle,
+    steps: u64,
+    bytes: usize,
+    calls: usize,
+    deadline: Option<Instant>
+}
+
+impl Sandbox {
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    //a fresh budget for the next run
+    pub fn start(&mut self) {
+        self.cancel.cancelled.store(false, Ordering::Relaxed);
+        self.steps = 0;
+        self.bytes = 0;
+        self.calls = 0;
+        self.deadline = self.limits.timeout.map(|timeout| Instant::now() + timeout);
+    }
+
+    pub fn step(&mut self) -> Result<(), LanguageError> {
+        self.steps += 1;
+        if self.cancel.is_cancelled() {
+            return Err(LanguageError::LimitError(String::from("Cancelled")));
+        }
+        if let Some(max) = self.limits.max_steps.filter(|max| self.steps > *max) {
+            return Err(LanguageError::LimitError(format!("Step limit of {} reached", max)));
+        }
+        match (self.deadline, self.limits.timeout) {
+            (Some(deadline), Some(timeout)) if self.steps.is_multiple_of(CLOCK_INTERVAL) && Instant::now() >= deadline => {
+                Err(LanguageError::LimitError(format!("Time limit of {:?} reached", timeout)))
+            },
+            _ => Ok(())
+        }
+    }
+
+    pub fn charge(&mut self, bytes: usize) -> Result<(), LanguageError> {
+        self.bytes += bytes;
+        match self.limits.max_memory {
+            Some(max) if self.bytes > max => Err(LanguageError::LimitError(format!("Memory limit of {} bytes reached", max))),
+            _ => Ok(())
+        }
+    }
+
+    pub fn enter_call(&mut self) -> Result<(), LanguageError> {
+        match self.limits.max_call_depth {
+            Some(max) if self.calls >= max => Err(LanguageError::LimitError(format!("Call depth limit of {} reached", max))),
+            _ => {
+                self.calls += 1;
+                Ok(())
+            }
+        }
+    }
+
+    pub fn leave_call(&mut self) {
+        self.calls -= 1;
+    }
+}
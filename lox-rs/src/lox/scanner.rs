@@ -1,7 +1,8 @@
 use super::errors::LanguageError;
+use super::symbol::Symbol;
 use std::fmt;
 
-#[derive(Debug,Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
     // Single-char tokens
     LEFTPAREN,
@@ -14,6 +15,9 @@ pub enum TokenType {
     PLUS,
     SEMICOLON,
     SLASH,
+    QUESTION,
+    COLON,
+    PERCENT,
     STAR,
     // One or two char tokens
     BANG,
@@ -24,10 +28,19 @@ pub enum TokenType {
     GREATEREQUAL,
     LESS,
     LESSEQUAL,
+    ARROW,
+    STARSTAR,
+    TILDESLASH,
+    PLUSEQUAL,
+    MINUSEQUAL,
+    STAREQUAL,
+    SLASHEQUAL,
+    PERCENTEQUAL,
     //Literals
-    IDENTIFIER(String),
+    IDENTIFIER(Symbol),
     STRING(String),
     NUMBER(f64),
+    DOC(String),
     //Keywords
     AND,
     CLASS,
@@ -48,11 +61,14 @@ pub enum TokenType {
     EOF
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
-    pub line: i32
+    pub line: i32,
+    //whitespace and comments around the token, only filled in lossless mode
+    pub leading_trivia: String,
+    pub trailing_trivia: String
 }
 
 impl fmt::Display for Token {
@@ -68,7 +84,9 @@ pub struct Scanner {
     start: usize,
     current: usize,
     line: i32,
-    errors: Vec<LanguageError>
+    errors: Vec<LanguageError>,
+    lossless: bool,
+    offsets: Vec<usize>
 }
 
 impl Scanner {
@@ -79,40 +97,61 @@ impl Scanner {
            start: 0,
            current: 0,
            line:1,
-           errors: Vec::new()
+           errors: Vec::new(),
+           lossless: false,
+           offsets: Vec::new()
        }
     }
+
+    //keeps whitespace and comments as trivia on the tokens, so that
+    //concatenating leading trivia, lexeme and trailing trivia of every
+    //token gives back the source byte-for-byte
+    pub fn lossless(source: String) -> Scanner {
+        Scanner {
+            lossless: true,
+            ..Scanner::new(source)
+        }
+    }
+
     fn add_token(&mut self, token: TokenType) {
        self.tokens.push(
            Token {
                token_type: token,
                lexeme: String::from(&self.source[self.start..self.current]),
-               line: self.line
+               line: self.line,
+               leading_trivia: String::new(),
+               trailing_trivia: String::new()
            });
+       self.offsets.push(self.start);
 
        self.start = self.current;
     }
+    //current is a byte offset, so it moves by the width of the char
     fn advance(&mut self) -> &str {
-        self.current+=1;
+        self.current += self.peek().len_utf8();
         &self.source[self.start..self.current]
     }
     
-    fn retreat(&mut self) -> &str {
-        self.current-=1;
-        &self.source[self.start..self.current]
+    //consumes the next char only if it is the expected one
+    fn next_is(&mut self, expected: char) -> bool {
+        if self.peek() == expected {
+            self.advance();
+            true
+        }else{
+            false
+        }
     }
     fn peek(&self) -> char {
         self.peek_offset(0)
     }
 
+    //current is a byte offset, only the chars from there on are walked so
+    //scanning stays linear in the length of the source
     fn peek_offset(&self, offset: usize) -> char {
-        if let Some(ch) = self.source
-            .chars()
-            .nth(self.current + offset) {
-                ch
-            }else{
-                '\0'
-            }
+        self.source
+            .get(self.current..)
+            .and_then(|rest| rest.chars().nth(offset))
+            .unwrap_or('\0')
     }
 
     pub fn scan_tokens(&mut self) -> Result<&Vec<Token>, &Vec<LanguageError>> {
@@ -126,51 +165,103 @@ impl Scanner {
                 "}" => self.add_token(TokenType::RIGHTBRACE),
                 "," => self.add_token(TokenType::COMMA),
                 "." => self.add_token(TokenType::DOT),
-                "-" => self.add_token(TokenType::MINUS),
-                "+" => self.add_token(TokenType::PLUS),
+                "-" => {
+                        if self.next_is('=') {
+                            self.add_token(TokenType::MINUSEQUAL);
+                        } else {
+                            self.add_token(TokenType::MINUS);
+                        }
+                },
+                "+" => {
+                        if self.next_is('=') {
+                            self.add_token(TokenType::PLUSEQUAL);
+                        } else {
+                            self.add_token(TokenType::PLUS);
+                        }
+                },
                 ";" => self.add_token(TokenType::SEMICOLON),
-                "*" => self.add_token(TokenType::STAR),
+                "*" => {
+                        if self.next_is('*') {
+                            self.add_token(TokenType::STARSTAR);
+                        } else if self.next_is('=') {
+                            self.add_token(TokenType::STAREQUAL);
+                        } else {
+                            self.add_token(TokenType::STAR);
+                        }
+                },
+                "%" => {
+                        if self.next_is('=') {
+                            self.add_token(TokenType::PERCENTEQUAL);
+                        } else {
+                            self.add_token(TokenType::PERCENT);
+                        }
+                },
+                //integer division, `//` is already taken by comments
+                "~" => {
+                        if self.next_is('/') {
+                            self.add_token(TokenType::TILDESLASH);
+                        } else {
+                            self.unexpected();
+                        }
+                },
+                "?" => self.add_token(TokenType::QUESTION),
+                ":" => self.add_token(TokenType::COLON),
                 "!" => {
-                        if self.advance() == "!=" {
+                        if self.next_is('=') {
                             self.add_token(TokenType::BANGEQUAL);
                         } else {
-                            self.retreat();
                             self.add_token(TokenType::BANG);
                         }
                 },
                 "=" => {
-                        if self.advance() == "==" {
+                        if self.next_is('=') {
                             self.add_token(TokenType::EQUALEQUAL);
+                        } else if self.next_is('>') {
+                            self.add_token(TokenType::ARROW);
                         } else {
-                            self.retreat();
                             self.add_token(TokenType::EQUAL);
                         }
                 },
                 ">" => {
-                        if self.advance() == ">=" {
+                        if self.next_is('=') {
                             self.add_token(TokenType::GREATEREQUAL);
                         } else {
-                            self.retreat();
                             self.add_token(TokenType::GREATER);
                         }
                 }
                 "<" => {
-                        if self.advance() == "<=" {
+                        if self.next_is('=') {
                             self.add_token(TokenType::LESSEQUAL);
                         } else {
-                            self.retreat();
                             self.add_token(TokenType::LESS);
                         }
                 }
                 "/" => {
-                        if self.advance() == "//" {
+                        if self.peek() == '/' && self.peek_offset(1) == '/'
+                            && self.peek_offset(2) != '/' {
+                            //doc comment
+                            self.advance();
+                            self.advance();
+                            while self.peek() != '\n' && self.current < self.source.len() {
+                                self.advance();
+                            }
+                            let doc = self.source[self.start+3..self.current].trim().to_string();
+                            self.add_token(TokenType::DOC(doc));
+                        }else if self.peek() == '/' {
                             //comment
-                            while self.advance() != "\n" 
-                                   && self.current < self.source.len() {
+                            while self.peek() != '\n' && self.current < self.source.len() {
+                                self.advance();
+                            }
+                            self.start = self.current;
+                        }else if self.peek() == '*' {
+                            self.advance();
+                            if let Err(e) = self.skip_block_comment() {
+                                self.errors.push(e);
                             }
-                               self.start = self.current;
+                            self.start = self.current;
+                        }else if self.next_is('=') {
+                            self.add_token(TokenType::SLASHEQUAL);
                         }else{
-                            self.retreat();
                             self.add_token(TokenType::SLASH);
                         }
                 }
@@ -204,13 +295,17 @@ impl Scanner {
                     self.keyword_or_identifier()
                     
                 },
-                e => eprintln!("Unexpected token {:?}", e)
+                _ => self.unexpected()
 
             }
         }
 
         self.add_token(TokenType::EOF);
 
+        if self.lossless {
+            self.attach_trivia();
+        }
+
         //check for syntax errors
         if !self.has_errors() {
             Ok(&self.tokens)
@@ -219,6 +314,23 @@ impl Scanner {
         }
     }
 
+    //tokens scanned so far, still available when scanning failed
+    pub fn tokens(&self) -> &Vec<Token> {
+        &self.tokens
+    }
+
+    pub fn errors(&self) -> &Vec<LanguageError> {
+        &self.errors
+    }
+
+    //a character no token starts with, it is skipped so scanning can go on
+    //and report the rest
+    fn unexpected(&mut self) {
+        let found = self.source[self.start..self.current].to_string();
+        self.errors.push(LanguageError::SyntaxError(format!("Unexpected character {:?} on line {}", found, self.line)));
+        self.start = self.current;
+    }
+
     fn keyword_or_identifier(&mut self) {
         
         self.add_token(
@@ -239,7 +351,7 @@ impl Scanner {
             "true" => TokenType::TRUE,
             "var" => TokenType::VAR,
             "while" => TokenType::WHILE,
-            s => TokenType::IDENTIFIER(s.into())
+            s => TokenType::IDENTIFIER(Symbol::intern(s))
         });
     }
 
@@ -249,7 +361,7 @@ impl Scanner {
         }
 
         if self.peek() == '.' &&
-            is_digit(self.peek_offset(1 as usize)) {
+            is_digit(self.peek_offset(1)) {
             self.advance();
 
             while is_digit(self.peek()){self.advance();}
@@ -271,7 +383,7 @@ impl Scanner {
         }
 
         if self.current == self.source.len() {
-            Err(LanguageError::SyntaxError("Unterminated String".into()))
+            Err(LanguageError::IncompleteInput("Unterminated String".into()))
         }else{
             self.advance();
             Ok(self.source[self.start+1..self.current-1].into())
@@ -279,11 +391,85 @@ impl Scanner {
     }
 
 
+    // block comments nest, so keep track of how deep we are
+    fn skip_block_comment(&mut self) -> Result<(), LanguageError> {
+        let line = self.line;
+        let mut depth = 1;
+        while depth > 0 {
+            if self.current >= self.source.len() {
+                return Err(LanguageError::IncompleteInput(format!("Unterminated block comment starting at line {}", line)));
+            }
+
+            match (self.peek(), self.peek_offset(1)) {
+                ('/', '*') => {
+                    self.advance();
+                    depth += 1;
+                },
+                ('*', '/') => {
+                    self.advance();
+                    depth -= 1;
+                },
+                ('\n', _) => self.line += 1,
+                _ => {}
+            }
+            self.advance();
+        }
+
+        Ok(())
+    }
+
+    //the gap after a token up to and including the first line break is its
+    //trailing trivia, the rest of the gap leads the next token
+    fn attach_trivia(&mut self) {
+        let mut gap_start = 0;
+
+        for (i, offset) in self.offsets.iter().enumerate() {
+            let gap = &self.source[gap_start..*offset];
+            let split = if i > 0 { trailing_len(gap) } else { 0 };
+
+            if i > 0 {
+                self.tokens[i - 1].trailing_trivia = gap[..split].into();
+            }
+            self.tokens[i].leading_trivia = gap[split..].into();
+
+            gap_start = offset + self.tokens[i].lexeme.len();
+        }
+    }
+
     fn has_errors(&self) -> bool {
         !self.errors.is_empty()
     }
 }
 
+//length of the part of a gap that stays on the line of the previous token,
+//block comments spanning multiple lines are not split
+fn trailing_len(gap: &str) -> usize {
+    let bytes = gap.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\n' => return i + 1,
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let mut depth = 1;
+                i += 2;
+                while i < bytes.len() && depth > 0 {
+                    if bytes[i] == b'/' && bytes.get(i + 1) == Some(&b'*') {
+                        depth += 1;
+                        i += 1;
+                    } else if bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/') {
+                        depth -= 1;
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            },
+            _ => i += 1
+        }
+    }
+
+    bytes.len()
+}
+
 fn is_alpha(chs: &str) -> bool {
         if let Some(ch) = chs.chars().next() {
             ch.is_alphabetic()
@@ -293,5 +479,5 @@ fn is_alpha(chs: &str) -> bool {
 }
 
 fn is_digit(ch: char) -> bool {
-    ch >= '0' && ch <= '9'
+    ch.is_ascii_digit()
 }
@@ -0,0 +1,59 @@
+use super::ast::{Environment, Function};
+use super::atom::Atom;
+use super::format;
+use super::symbol::Symbol;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const WIDTH: usize = 80;
+
+//writes the globals out as Lox source that declares them again, so
+//restoring a session is running the file. Functions are saved as their
+//source and look up globals when called, so their order doesn't matter,
+//a variable that is only another name for a function comes last. What
+//can't be written as source is left as a comment: a closure loses the
+//locals it captured, and host objects and strings containing `"`, or
+//functions with such a string in them, aren't saved at all. Natives are
+//left to the host to register again
+pub fn save(globals: &Rc<RefCell<Environment>>) -> String {
+    let mut out = String::from("//rlox session\n");
+    let mut aliases = String::new();
+    let vars = globals.borrow().vars();
+    for (name, value) in vars.iter() {
+        match value {
+            Atom::Function(fun) => match alias_of(name, fun, &vars) {
+                Some(own) => aliases.push_str(&format!("var {} = {};\n", name, own)),
+                None => {
+                    if !Rc::ptr_eq(&fun.closure, globals) {
+                        out.push_str(&format!("//{} closed over local variables, they are not saved\n", name));
+                    }
+                    let source = if fun.decl().name.as_ref() == Some(name) {
+                        format::function(&fun.ast, fun.decl, WIDTH).map(|source| format!("{}\n", source))
+                    }else{
+                        format::lambda(&fun.ast, fun.decl, WIDTH).map(|source| format!("var {} = {};\n", name, source))
+                    };
+                    match source {
+                        Ok(source) => out.push_str(&source),
+                        Err(_) => out.push_str(&format!("//{} contains a string with \", it is not saved\n", name))
+                    }
+                }
+            },
+            Atom::Native(native) if native.name == *name => {},
+            Atom::Native(native) => out.push_str(&format!("var {} = {};\n", name, native.name)),
+            Atom::Object(object) => out.push_str(&format!("//{} is a host {}, it is not saved\n", name, object.type_name())),
+            value => match format::literal(value) {
+                Some(literal) => out.push_str(&format!("var {} = {};\n", name, literal)),
+                None => out.push_str(&format!("//{} is a string containing \", it is not saved\n", name))
+            }
+        }
+    }
+    out + &aliases
+}
+
+//the name a function is saved under when name is only another global
+//for it
+fn alias_of(name: &Symbol, fun: &Rc<Function>, vars: &[(Symbol, Atom)]) -> Option<Symbol> {
+    let own = fun.decl().name.clone().filter(|own| own != name)?;
+    vars.iter().any(|(other, value)| *other == own && matches!(value, Atom::Function(saved) if Rc::ptr_eq(saved, fun))).then_some(own)
+}
@@ -0,0 +1,205 @@
+use super::json::{Json, MAX_NESTING};
+
+use std::fmt;
+
+//the JSON schema written as S-expressions, so both formats describe the
+//same tree and load through the same code. An object with a "type" is
+//written `(Type :key value ...)`, any other object `(:key value ...)` and
+//an array `(item ...)`. An object with no fields is `(:)`, so it doesn't
+//read back as an empty array. null, true and false are nil, true and false
+pub struct Sexp<'a>(pub &'a Json);
+
+impl fmt::Display for Sexp<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Json::Null => write!(f, "nil"),
+            Json::Array(items) => {
+                write!(f, "(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", Sexp(item))?;
+                }
+                write!(f, ")")
+            },
+            Json::Object(fields) => {
+                write!(f, "(")?;
+                let fields = match fields.split_first() {
+                    Some(((key, Json::String(kind)), rest)) if key == "type" && is_symbol(kind) => {
+                        write!(f, "{}", kind)?;
+                        if !rest.is_empty() {
+                            write!(f, " ")?;
+                        }
+                        rest
+                    },
+                    _ if fields.is_empty() => return write!(f, ":)"),
+                    _ => fields
+                };
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, ":{} {}", key, Sexp(value))?;
+                }
+                write!(f, ")")
+            },
+            //strings and numbers are written the same as in JSON
+            other => write!(f, "{}", other)
+        }
+    }
+}
+
+//types are written bare, so they have to read back as a symbol
+fn is_symbol(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !s.starts_with(|c: char| c.is_ascii_digit())
+        && !matches!(s, "nil" | "true" | "false")
+}
+
+enum Item {
+    Value(Json),
+    Symbol(String),
+    Keyword(String)
+}
+
+//reads back what Sexp writes
+pub fn parse(source: &str) -> Result<Json, String> {
+    let mut reader = Reader { chars: source.chars().collect(), current: 0, depth: 0 };
+    let json = match reader.item()? {
+        Item::Value(json) => json,
+        Item::Symbol(s) | Item::Keyword(s) => return Err(format!("Unexpected {}", s))
+    };
+
+    reader.skip_whitespace();
+    if reader.current < reader.chars.len() {
+        return Err("Unexpected input after S-expression".into());
+    }
+    Ok(json)
+}
+
+struct Reader {
+    chars: Vec<char>,
+    current: usize,
+    //lists we are in
+    depth: usize
+}
+
+impl Reader {
+    fn item(&mut self) -> Result<Item, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('(') => {
+                self.advance();
+                if self.depth >= MAX_NESTING {
+                    return Err(format!("Nesting deeper than {} levels", MAX_NESTING));
+                }
+                self.depth += 1;
+                let items = self.items();
+                self.depth -= 1;
+                list(items?).map(Item::Value)
+            },
+            Some(')') => Err("Unexpected )".into()),
+            Some('"') => {
+                //strings use JSON escapes
+                let start = self.current;
+                self.advance();
+                while let Some(c) = self.advance() {
+                    match c {
+                        '\\' => { self.advance(); },
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+                let text: String = self.chars[start..self.current.min(self.chars.len())].iter().collect();
+                Json::parse(&text).map(Item::Value)
+            },
+            Some(_) => {
+                let start = self.current;
+                while matches!(self.peek(), Some(c) if !c.is_whitespace() && c != '(' && c != ')' && c != '"') {
+                    self.advance();
+                }
+                let text: String = self.chars[start..self.current].iter().collect();
+                match text.as_str() {
+                    "nil" => Ok(Item::Value(Json::Null)),
+                    "true" => Ok(Item::Value(Json::Bool(true))),
+                    "false" => Ok(Item::Value(Json::Bool(false))),
+                    _ if text.starts_with(':') => Ok(Item::Keyword(text[1..].to_string())),
+                    _ if text.starts_with(|c: char| c == '-' || c.is_ascii_digit()) => {
+                        text.parse::<f64>().map(|n| Item::Value(Json::Number(n))).map_err(|_| format!("Invalid number {}", text))
+                    },
+                    _ => Ok(Item::Symbol(text))
+                }
+            },
+            None => Err("Unexpected end of S-expression".into())
+        }
+    }
+
+    //everything after the opening paren, up to and including the closing one
+    fn items(&mut self) -> Result<Vec<Item>, String> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(')') => {
+                    self.advance();
+                    return Ok(items);
+                },
+                Some(_) => items.push(self.item()?),
+                None => return Err("Unterminated list".into())
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_whitespace()) {
+            self.advance();
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.current).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.current += 1;
+        c
+    }
+}
+
+//a list is an object when it starts with a type or a key, otherwise an array
+fn list(items: Vec<Item>) -> Result<Json, String> {
+    if let [Item::Keyword(key)] = items.as_slice() {
+        if key.is_empty() {
+            return Ok(Json::Object(Vec::new()));
+        }
+    }
+
+    let mut items = items.into_iter().peekable();
+    let mut fields: Vec<(String, Json)> = Vec::new();
+    match items.peek() {
+        Some(Item::Symbol(_)) => {
+            if let Some(Item::Symbol(kind)) = items.next() {
+                fields.push(("type".into(), Json::String(kind)));
+            }
+        },
+        Some(Item::Keyword(_)) => {},
+        _ => {
+            return items.map(|item| match item {
+                Item::Value(json) => Ok(json),
+                Item::Symbol(s) | Item::Keyword(s) => Err(format!("Unexpected {} in list", s))
+            }).collect::<Result<Vec<Json>, String>>().map(Json::Array);
+        }
+    }
+
+    while let Some(item) = items.next() {
+        match (item, items.next()) {
+            (Item::Keyword(key), Some(Item::Value(value))) => fields.push((key, value)),
+            (Item::Keyword(key), _) => return Err(format!("Expected a value for :{}", key)),
+            _ => return Err("Expected :key value pairs".into())
+        }
+    }
+    Ok(Json::Object(fields))
+}
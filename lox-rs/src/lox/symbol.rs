@@ -0,0 +1,100 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+//an interned identifier, cheap to clone, compare and hash: two symbols
+//with the same name share one allocation, so they are compared by
+//address. Like the Rc values the interpreter is built from a symbol stays
+//on the thread that interned it
+#[derive(Clone)]
+pub struct Symbol(Rc<str>);
+
+//the names interned on this thread. A name nothing refers to any more is
+//dropped the next time the table has doubled since it was last swept, so
+//it never holds much more than the names still in use
+#[derive(Default)]
+struct Interner {
+    names: HashSet<Rc<str>>,
+    swept_at: usize
+}
+
+//the fewest names worth sweeping for
+const SWEEP_MIN: usize = 64;
+
+thread_local! {
+    static INTERNER: RefCell<Interner> = RefCell::new(Interner::default());
+}
+
+impl Interner {
+    fn intern(&mut self, name: &str) -> Rc<str> {
+        if let Some(name) = self.names.get(name) {
+            return name.clone();
+        }
+
+        if self.names.len() >= SWEEP_MIN.max(self.swept_at * 2) {
+            self.names.retain(|name| Rc::strong_count(name) > 1);
+            self.swept_at = self.names.len();
+        }
+
+        let name: Rc<str> = Rc::from(name);
+        self.names.insert(name.clone());
+        name
+    }
+}
+
+impl Symbol {
+    pub fn intern(name: &str) -> Symbol {
+        Symbol(INTERNER.with(|interner| interner.borrow_mut().intern(name)))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    //how many names this thread's table holds, including any not yet swept
+    pub fn interned() -> usize {
+        INTERNER.with(|interner| interner.borrow().names.len())
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, rhs: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &rhs.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::ptr::hash(Rc::as_ptr(&self.0) as *const u8, state)
+    }
+}
+
+//by name, so an ordering doesn't depend on where the names were allocated
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, rhs: &Self) -> Option<Ordering> {
+        Some(self.cmp(rhs))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, rhs: &Self) -> Ordering {
+        self.0.cmp(&rhs.0)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.name())
+    }
+}
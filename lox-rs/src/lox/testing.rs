@@ -0,0 +1,291 @@
+use super::ast::{Ast, Expr, ExprId, FunctionDecl, FunctionId, Span, Stmt, StmtId};
+use super::atom::Atom;
+use super::format;
+use super::json;
+use super::parser::Parser;
+use super::scanner::{Scanner, Token, TokenType};
+use super::symbol::Symbol;
+
+//how tightly each kind of expression binds, loosest first. This is kept
+//apart from the parser's own table on purpose, it is what the parser is
+//checked against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    Comma,
+    Assignment,
+    Conditional,
+    Equality,
+    Comparison,
+    Term,
+    Factor,
+    Unary,
+    Exponent,
+    Call,
+    Primary
+}
+
+//operator, lexeme, level, right associative
+const BINARY: &[(TokenType, &str, Level, bool)] = &[
+    (TokenType::COMMA, ",", Level::Comma, false),
+    (TokenType::EQUALEQUAL, "==", Level::Equality, false),
+    (TokenType::BANGEQUAL, "!=", Level::Equality, false),
+    (TokenType::LESS, "<", Level::Comparison, false),
+    (TokenType::LESSEQUAL, "<=", Level::Comparison, false),
+    (TokenType::GREATER, ">", Level::Comparison, false),
+    (TokenType::GREATEREQUAL, ">=", Level::Comparison, false),
+    (TokenType::PLUS, "+", Level::Term, false),
+    (TokenType::MINUS, "-", Level::Term, false),
+    (TokenType::STAR, "*", Level::Factor, false),
+    (TokenType::SLASH, "/", Level::Factor, false),
+    (TokenType::PERCENT, "%", Level::Factor, false),
+    (TokenType::TILDESLASH, "~/", Level::Factor, false),
+    (TokenType::STARSTAR, "**", Level::Exponent, true)
+];
+
+const ASSIGN: &[(TokenType, &str)] = &[
+    (TokenType::EQUAL, "="),
+    (TokenType::PLUSEQUAL, "+="),
+    (TokenType::MINUSEQUAL, "-="),
+    (TokenType::STAREQUAL, "*="),
+    (TokenType::SLASHEQUAL, "/="),
+    (TokenType::PERCENTEQUAL, "%=")
+];
+
+const UNARY: &[(TokenType, &str)] = &[
+    (TokenType::MINUS, "-"),
+    (TokenType::BANG, "!")
+];
+
+const NAMES: &[&str] = &["a", "b", "c", "count", "total", "f", "g", "tmp_1", "x1"];
+
+const WORDS: &[&str] = &["", "lox", "hello world", "two\nlines", "a b c"];
+
+//random programs for testing the front end. The same seed always gives the
+//same program. Trees are built the way the parser would have built them,
+//with a Grouping wherever the source needs parentheses, so printing one
+//and parsing it back should give the same tree
+pub struct Generator {
+    rng: Rng,
+    //how much deeper the tree may still go
+    depth: usize,
+    //return is only generated inside a function
+    functions: usize
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Self {
+        Generator { rng: Rng::new(seed), depth: 5, functions: 0 }
+    }
+
+    //a program of a few top level statements
+    pub fn program(&mut self, ast: &mut Ast) -> Vec<StmtId> {
+        let count = 1 + self.below(6);
+        (0..count).map(|_| self.stmt(ast)).collect()
+    }
+
+    fn stmt(&mut self, ast: &mut Ast) -> StmtId {
+        let stmt = self.nested(|gen| {
+            let kinds = if gen.functions > 0 { 6 } else { 5 };
+            match gen.below(kinds) {
+                0 if gen.depth > 0 => {
+                    let count = gen.below(4);
+                    Stmt::Block((0..count).map(|_| gen.stmt(ast)).collect())
+                },
+                1 if gen.depth > 0 => {
+                    let name = gen.name();
+                    Stmt::Function(gen.function(ast, Some(name)), None)
+                },
+                2 => Stmt::Print(gen.expr(ast, Level::Comma)),
+                3 => {
+                    let init = if gen.chance(4) { gen.literal(ast, Atom::Nil) } else { gen.expr(ast, Level::Comma) };
+                    Stmt::Var(gen.name(), init, None)
+                },
+                5 => {
+                    let value = if gen.chance(4) { gen.literal(ast, Atom::Nil) } else { gen.expr(ast, Level::Comma) };
+                    Stmt::Return(value)
+                },
+                _ => Stmt::Expr(gen.expr(ast, Level::Comma))
+            }
+        });
+        ast.add_stmt(stmt, Span::default())
+    }
+
+    //an expression that can stand where the parser expects one binding at
+    //least as tight as min
+    fn expr(&mut self, ast: &mut Ast, min: Level) -> ExprId {
+        if self.depth == 0 || self.chance(3) {
+            return self.leaf(ast);
+        }
+
+        let (expr, level) = self.nested(|gen| match gen.below(9) {
+            0 => {
+                let (token_type, lexeme, level, right) = BINARY[gen.below(BINARY.len())].clone();
+                let (lhs_min, rhs_min) = if right { (next(level), level) } else { (level, next(level)) };
+                let lhs = gen.expr(ast, lhs_min);
+                let rhs = gen.expr(ast, rhs_min);
+                (Expr::Binary(lhs, token(token_type, lexeme), rhs), level)
+            },
+            1 => {
+                let (token_type, lexeme) = ASSIGN[gen.below(ASSIGN.len())].clone();
+                let value = gen.expr(ast, Level::Assignment);
+                (Expr::Assign(gen.name(), token(token_type, lexeme), value), Level::Assignment)
+            },
+            2 => {
+                let callee = gen.expr(ast, Level::Call);
+                let count = gen.below(4);
+                let args = (0..count).map(|_| gen.expr(ast, Level::Assignment)).collect();
+                (Expr::Call(callee, token(TokenType::RIGHTPAREN, ")"), args), Level::Call)
+            },
+            3 => (Expr::Grouping(gen.expr(ast, Level::Comma)), Level::Primary),
+            4 => (Expr::Lambda(gen.function(ast, None)), Level::Primary),
+            7 => (Expr::Get(gen.expr(ast, Level::Call), gen.name()), Level::Call),
+            8 => {
+                let object = gen.expr(ast, Level::Call);
+                let (token_type, lexeme) = ASSIGN[gen.below(ASSIGN.len())].clone();
+                let value = gen.expr(ast, Level::Assignment);
+                (Expr::Set(object, gen.name(), token(token_type, lexeme), value), Level::Assignment)
+            },
+            5 => {
+                let cond = gen.expr(ast, next(Level::Conditional));
+                let then = gen.expr(ast, Level::Comma);
+                let otherwise = gen.expr(ast, Level::Conditional);
+                (Expr::Conditional(cond, then, otherwise), Level::Conditional)
+            },
+            _ => {
+                let (token_type, lexeme) = UNARY[gen.below(UNARY.len())].clone();
+                (Expr::Unary(token(token_type, lexeme), gen.expr(ast, Level::Unary)), Level::Unary)
+            }
+        });
+
+        let id = ast.add_expr(expr, Span::default());
+        if level < min {
+            ast.add_expr(Expr::Grouping(id), Span::default())
+        }else{
+            id
+        }
+    }
+
+    fn function(&mut self, ast: &mut Ast, name: Option<Symbol>) -> FunctionId {
+        let count = self.below(4);
+        let params = (0..count).map(|_| self.name()).collect();
+
+        self.functions += 1;
+        let count = self.below(4);
+        let body = (0..count).map(|_| self.stmt(ast)).collect();
+        self.functions -= 1;
+
+        ast.add_function(FunctionDecl { name, params, body })
+    }
+
+    fn leaf(&mut self, ast: &mut Ast) -> ExprId {
+        match self.below(6) {
+            0 => self.literal(ast, Atom::Nil),
+            1 => {
+                let value = self.chance(2);
+                self.literal(ast, Atom::Bool(value))
+            },
+            2 => {
+                let value = self.below(1000) as f64 / [1.0, 4.0, 10.0][self.below(3)];
+                self.literal(ast, Atom::Number(value))
+            },
+            3 => {
+                let value = WORDS[self.below(WORDS.len())].to_string();
+                self.literal(ast, Atom::String(value))
+            },
+            _ => {
+                let name = self.name();
+                ast.add_expr(Expr::Var(name), Span::default())
+            }
+        }
+    }
+
+    fn literal(&mut self, ast: &mut Ast, value: Atom) -> ExprId {
+        ast.add_expr(Expr::Literal(value), Span::default())
+    }
+
+    fn name(&mut self) -> Symbol {
+        Symbol::intern(NAMES[self.below(NAMES.len())])
+    }
+
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.depth = self.depth.saturating_sub(1);
+        let result = f(self);
+        self.depth += 1;
+        result
+    }
+
+    //one in n
+    fn chance(&mut self, n: usize) -> bool {
+        self.below(n) == 0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        self.rng.below(n)
+    }
+}
+
+//xorshift, good enough for reproducible random tests
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        //xorshift gets stuck on zero
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    //a number in 0..n
+    pub fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+fn next(level: Level) -> Level {
+    match level {
+        Level::Comma => Level::Assignment,
+        Level::Assignment => Level::Conditional,
+        Level::Conditional => Level::Equality,
+        Level::Equality => Level::Comparison,
+        Level::Comparison => Level::Term,
+        Level::Term => Level::Factor,
+        Level::Factor => Level::Unary,
+        Level::Unary => Level::Exponent,
+        Level::Exponent => Level::Call,
+        Level::Call | Level::Primary => Level::Primary
+    }
+}
+
+fn token(token_type: TokenType, lexeme: &str) -> Token {
+    Token { token_type, lexeme: lexeme.into(), line: 0, leading_trivia: String::new(), trailing_trivia: String::new() }
+}
+
+//generates a program from seed, prints it at width and parses it back.
+//Any difference in the trees comes back as the printed source and what
+//went wrong
+pub fn round_trip(seed: u64, width: usize) -> Result<(), String> {
+    let mut ast = Ast::default();
+    let stmts = Generator::new(seed).program(&mut ast);
+    let source = format::program(&ast, &stmts, width).map_err(|err| format!("seed {}: {}", seed, err))?;
+
+    let mut scanner = Scanner::new(source.clone());
+    let tokens = match scanner.scan_tokens() {
+        Ok(tokens) => tokens.clone(),
+        Err(errs) => return Err(format!("seed {}: {:?}\n{}", seed, errs, source))
+    };
+    let mut parsed = Ast::default();
+    let mut parser = Parser::new(&tokens, &mut parsed);
+    let parsed_stmts = match parser.parse() {
+        Ok(stmts) => stmts,
+        Err(_) => return Err(format!("seed {}: {:?}\n{}", seed, parser.errors(), source))
+    };
+
+    let expected = json::shape(&json::program(&ast, &stmts));
+    let found = json::shape(&json::program(&parsed, &parsed_stmts));
+    if expected == found {
+        Ok(())
+    }else{
+        Err(format!("seed {}: parsed back differently\n{}\nexpected {}\nfound    {}", seed, source, expected, found))
+    }
+}
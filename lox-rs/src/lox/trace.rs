@@ -0,0 +1,96 @@
+use super::ast::{Ast, Expr, ExprId, Span, Stmt, StmtId};
+use super::atom::Atom;
+use super::errors::LanguageError;
+
+use std::io::Write;
+
+//callbacks from a running interpreter, for debuggers, profilers and
+//coverage tools. Every method does nothing by default. Without a hook the
+//interpreter skips all of this
+pub trait Hook {
+    //before a statement runs
+    fn statement(&mut self, _ast: &Ast, _id: StmtId, _span: Span) {}
+
+    //after an expression was evaluated without error
+    fn expression(&mut self, _ast: &Ast, _id: ExprId, _span: Span, _value: &Atom) {}
+
+    //around a call of a Lox function or native, span is the call's. Every
+    //enter has an exit, the value is nil when the call failed
+    fn enter_function(&mut self, _callee: &Atom, _args: &[Atom], _span: Span) {}
+
+    fn exit_function(&mut self, _callee: &Atom, _value: &Atom, _span: Span) {}
+
+    //once per error, with the span of the innermost expression or
+    //statement it came from
+    fn error(&mut self, _err: &LanguageError, _span: Span) {}
+}
+
+//the hook behind `rlox --trace`, one line per event indented by call depth
+pub struct Tracer {
+    out: Box<dyn Write>,
+    depth: usize
+}
+
+impl Tracer {
+    pub fn new(out: impl Write + 'static) -> Self {
+        Tracer { out: Box::new(out), depth: 0 }
+    }
+
+    //a trace that can't be written is dropped rather than failing the
+    //program
+    fn line(&mut self, span: Span, text: &str) {
+        let _ = writeln!(self.out, "[line {}] {}{}", span.line, "  ".repeat(self.depth), text);
+    }
+}
+
+impl Hook for Tracer {
+    fn statement(&mut self, ast: &Ast, id: StmtId, span: Span) {
+        self.line(span, stmt_kind(ast.stmt(id)));
+    }
+
+    fn expression(&mut self, ast: &Ast, id: ExprId, span: Span, value: &Atom) {
+        self.line(span, &format!("  {} = {}", expr_kind(ast.expr(id)), value));
+    }
+
+    fn enter_function(&mut self, callee: &Atom, args: &[Atom], span: Span) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        self.line(span, &format!("-> {}({})", callee, args.join(", ")));
+        self.depth += 1;
+    }
+
+    fn exit_function(&mut self, callee: &Atom, value: &Atom, span: Span) {
+        self.depth = self.depth.saturating_sub(1);
+        self.line(span, &format!("<- {} = {}", callee, value));
+    }
+
+    fn error(&mut self, err: &LanguageError, span: Span) {
+        self.line(span, &format!("!! {}", err));
+    }
+}
+
+fn stmt_kind(stmt: &Stmt) -> &'static str {
+    match stmt {
+        Stmt::Block(_) => "Block",
+        Stmt::Expr(_) => "Expression",
+        Stmt::Function(..) => "Function",
+        Stmt::Print(_) => "Print",
+        Stmt::Return(_) => "Return",
+        Stmt::Var(..) => "Var"
+    }
+}
+
+fn expr_kind(expr: &Expr) -> &'static str {
+    match expr {
+        Expr::Assign(..) => "Assign",
+        Expr::Binary(..) => "Binary",
+        Expr::Call(..) => "Call",
+        Expr::Get(..) => "Get",
+        Expr::Set(..) => "Set",
+        Expr::Grouping(_) => "Grouping",
+        Expr::Lambda(_) => "Lambda",
+        Expr::Conditional(..) => "Conditional",
+        Expr::Literal(_) => "Literal",
+        Expr::Unary(..) => "Unary",
+        Expr::Var(_) => "Var"
+    }
+}
@@ -0,0 +1,144 @@
+use super::ast::{Ast, Expr, ExprId, FunctionId, Stmt, StmtId};
+use super::atom::Atom;
+use super::scanner::Token;
+use super::symbol::Symbol;
+
+//a read only pass over the tree with one method per kind of node. Every
+//method defaults to visiting the node's children, so a pass only overrides
+//the nodes it cares about and calls the matching walk_* function when it
+//still wants to go deeper
+pub trait Visitor<'ast> {
+    fn visit_stmt(&mut self, ast: &'ast Ast, id: StmtId) {
+        walk_stmt(self, ast, id)
+    }
+
+    fn visit_expr(&mut self, ast: &'ast Ast, id: ExprId) {
+        walk_expr(self, ast, id)
+    }
+
+    //named functions and lambdas both end up here
+    fn visit_function(&mut self, ast: &'ast Ast, id: FunctionId) {
+        walk_function(self, ast, id)
+    }
+
+    fn visit_block_stmt(&mut self, ast: &'ast Ast, _id: StmtId, stmts: &'ast [StmtId]) {
+        walk_block_stmt(self, ast, stmts)
+    }
+
+    fn visit_expr_stmt(&mut self, ast: &'ast Ast, _id: StmtId, expr: ExprId) {
+        self.visit_expr(ast, expr)
+    }
+
+    fn visit_function_stmt(&mut self, ast: &'ast Ast, _id: StmtId, decl: FunctionId, _doc: Option<&'ast str>) {
+        self.visit_function(ast, decl)
+    }
+
+    fn visit_print_stmt(&mut self, ast: &'ast Ast, _id: StmtId, expr: ExprId) {
+        self.visit_expr(ast, expr)
+    }
+
+    fn visit_return_stmt(&mut self, ast: &'ast Ast, _id: StmtId, expr: ExprId) {
+        self.visit_expr(ast, expr)
+    }
+
+    fn visit_var_stmt(&mut self, ast: &'ast Ast, _id: StmtId, _name: &'ast Symbol, init: ExprId, _doc: Option<&'ast str>) {
+        self.visit_expr(ast, init)
+    }
+
+    fn visit_assign(&mut self, ast: &'ast Ast, _id: ExprId, _name: &'ast Symbol, _op: &'ast Token, value: ExprId) {
+        self.visit_expr(ast, value)
+    }
+
+    fn visit_binary(&mut self, ast: &'ast Ast, _id: ExprId, lhs: ExprId, _op: &'ast Token, rhs: ExprId) {
+        walk_binary(self, ast, lhs, rhs)
+    }
+
+    fn visit_call(&mut self, ast: &'ast Ast, _id: ExprId, callee: ExprId, _paren: &'ast Token, args: &'ast [ExprId]) {
+        walk_call(self, ast, callee, args)
+    }
+
+    fn visit_get(&mut self, ast: &'ast Ast, _id: ExprId, object: ExprId, _name: &'ast Symbol) {
+        self.visit_expr(ast, object)
+    }
+
+    fn visit_set(&mut self, ast: &'ast Ast, _id: ExprId, object: ExprId, _name: &'ast Symbol, _op: &'ast Token, value: ExprId) {
+        walk_binary(self, ast, object, value)
+    }
+
+    fn visit_grouping(&mut self, ast: &'ast Ast, _id: ExprId, expr: ExprId) {
+        self.visit_expr(ast, expr)
+    }
+
+    fn visit_lambda(&mut self, ast: &'ast Ast, _id: ExprId, decl: FunctionId) {
+        self.visit_function(ast, decl)
+    }
+
+    fn visit_conditional(&mut self, ast: &'ast Ast, _id: ExprId, cond: ExprId, then: ExprId, otherwise: ExprId) {
+        walk_conditional(self, ast, cond, then, otherwise)
+    }
+
+    fn visit_literal(&mut self, _ast: &'ast Ast, _id: ExprId, _value: &'ast Atom) {}
+
+    fn visit_unary(&mut self, ast: &'ast Ast, _id: ExprId, _op: &'ast Token, rhs: ExprId) {
+        self.visit_expr(ast, rhs)
+    }
+
+    fn visit_var(&mut self, _ast: &'ast Ast, _id: ExprId, _name: &'ast Symbol) {}
+}
+
+//hands the statement to the visit_* method for its kind
+pub fn walk_stmt<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, ast: &'ast Ast, id: StmtId) {
+    match ast.stmt(id) {
+        Stmt::Block(stmts) => visitor.visit_block_stmt(ast, id, stmts),
+        Stmt::Expr(expr) => visitor.visit_expr_stmt(ast, id, *expr),
+        Stmt::Function(decl, doc) => visitor.visit_function_stmt(ast, id, *decl, doc.as_deref()),
+        Stmt::Print(expr) => visitor.visit_print_stmt(ast, id, *expr),
+        Stmt::Return(expr) => visitor.visit_return_stmt(ast, id, *expr),
+        Stmt::Var(name, init, doc) => visitor.visit_var_stmt(ast, id, name, *init, doc.as_deref())
+    }
+}
+
+//hands the expression to the visit_* method for its kind
+pub fn walk_expr<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, ast: &'ast Ast, id: ExprId) {
+    match ast.expr(id) {
+        Expr::Assign(name, op, value) => visitor.visit_assign(ast, id, name, op, *value),
+        Expr::Binary(lhs, op, rhs) => visitor.visit_binary(ast, id, *lhs, op, *rhs),
+        Expr::Call(callee, paren, args) => visitor.visit_call(ast, id, *callee, paren, args),
+        Expr::Get(object, name) => visitor.visit_get(ast, id, *object, name),
+        Expr::Set(object, name, op, value) => visitor.visit_set(ast, id, *object, name, op, *value),
+        Expr::Grouping(expr) => visitor.visit_grouping(ast, id, *expr),
+        Expr::Lambda(decl) => visitor.visit_lambda(ast, id, *decl),
+        Expr::Conditional(cond, then, otherwise) => visitor.visit_conditional(ast, id, *cond, *then, *otherwise),
+        Expr::Literal(value) => visitor.visit_literal(ast, id, value),
+        Expr::Unary(op, rhs) => visitor.visit_unary(ast, id, op, *rhs),
+        Expr::Var(name) => visitor.visit_var(ast, id, name)
+    }
+}
+
+pub fn walk_function<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, ast: &'ast Ast, id: FunctionId) {
+    walk_block_stmt(visitor, ast, &ast.function(id).body)
+}
+
+pub fn walk_block_stmt<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, ast: &'ast Ast, stmts: &'ast [StmtId]) {
+    for stmt in stmts {
+        visitor.visit_stmt(ast, *stmt);
+    }
+}
+
+pub fn walk_binary<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, ast: &'ast Ast, lhs: ExprId, rhs: ExprId) {
+    visitor.visit_expr(ast, lhs);
+    visitor.visit_expr(ast, rhs);
+}
+
+pub fn walk_call<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, ast: &'ast Ast, callee: ExprId, args: &'ast [ExprId]) {
+    visitor.visit_expr(ast, callee);
+    for arg in args {
+        visitor.visit_expr(ast, *arg);
+    }
+}
+
+pub fn walk_conditional<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, ast: &'ast Ast, cond: ExprId, then: ExprId, otherwise: ExprId) {
+    visitor.visit_expr(ast, cond);
+    visitor.visit_expr(ast, then);
+    visitor.visit_expr(ast, otherwise);
+}
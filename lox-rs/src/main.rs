@@ -1,71 +1,389 @@
 extern crate lox_rs;
 
 use lox_rs::lox;
-use lox_rs::lox::ast::StmtVisitor;
+use lox::ast::{Ast, AstPrinter, StmtId};
+use lox::engine::Engine;
+use lox::errors::LanguageError;
+use lox::json::Json;
+use lox::sexp::Sexp;
+use lox::resolver;
+use lox::scanner::{Scanner, Token};
+use std::io::{Read, Write};
+use std::{env, fs, io, process};
 
-use lox::ast::{Interpreter, Stmt};
-use lox::parser::Parser;
-use lox::scanner::Scanner;
-use std::io::Write;
-use std::{env, fs, io};
+const USAGE: &str = "Usage: rlox [--trace] [script]
+       rlox [--trace] --restore <session.lox-state>
+       rlox <tokens|ast|check|run> <file | - | -e code> [--format=text|json|sexp]
+       rlox ast <file | - | -e code> --format=dot [--scopes]
+       rlox load <file | - | -e ast> [--format=text|json|sexp]
+       rlox fmt [--check] [--width=N] <files... | ->";
 
-fn run(source: &String, intp: &mut Interpreter) -> Result<(), &'static str> {
-    let mut scanner: Scanner = Scanner::new(source.clone());
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Text,
+    Json,
+    Sexp,
+    Dot
+}
+
+struct Command {
+    stage: String,
+    source: String,
+    format: Format,
+    //overlay resolver scopes on --format=dot
+    scopes: bool
+}
+
+fn scan(source: &str) -> Result<Vec<Token>, Vec<LanguageError>> {
+    let mut scanner: Scanner = Scanner::new(source.to_string());
 
     match scanner.scan_tokens() {
-        Ok(tokens) => {
-            let mut parser: Parser = Parser::new(tokens);
-            let stmts: Vec<Stmt> = if let Ok(stmts) = parser.parse() {
-                stmts
-            } else {
-                return Err("Some errors occurred i guess idk");
-            };
+        Ok(tokens) => Ok(tokens.clone()),
+        Err(errs) => Err(errs.clone())
+    }
+}
 
-            for stmt in stmts {
-                intp.visit_stmt(&stmt);
-            }
-            Ok(())
-        }
-        Err(errs) => {
+fn parse(source: &str) -> Result<(Ast, Vec<StmtId>), Vec<LanguageError>> {
+    lox::engine::parse(source).map_err(|err| err.errors)
+}
+
+fn resolve(source: &str) -> Result<(Ast, Vec<StmtId>), Vec<LanguageError>> {
+    let (mut ast, stmts) = parse(source)?;
+    resolver::resolve(&mut ast, &stmts)?;
+    Ok((ast, stmts))
+}
+
+//loads an AST written by `ast --format=json` or `ast --format=sexp`
+fn load(source: &str) -> Result<(Ast, Vec<StmtId>), Vec<LanguageError>> {
+    let json = if source.trim_start().starts_with('(') {
+        lox::sexp::parse(source)
+    }else{
+        Json::parse(source)
+    };
+
+    json.and_then(|json| lox::json::load(&json))
+        .map_err(|err| vec![LanguageError::SyntaxError(err)])
+}
+
+//json and sexp output are the same tree written two ways
+fn emit(json: &Json, format: Format) {
+    match format {
+        Format::Sexp => println!("{}", Sexp(json)),
+        _ => println!("{}", json)
+    }
+}
+
+fn report(errs: &[LanguageError], format: Format) {
+    match format {
+        Format::Text | Format::Dot => {
             for err in errs.iter() {
                 eprintln!("{:?}", err);
             }
-            Err("Execution Error")
+        },
+        _ => emit(&Json::object(vec![
+            ("errors", Json::Array(errs.iter().map(Json::from).collect()))
+        ]), format)
+    }
+}
+
+//running a script or the prompt, `[--trace] [--restore <file> | script]`
+struct Options {
+    trace: bool,
+    restore: Option<String>,
+    script: Option<String>
+}
+
+fn parse_options(args: &[String]) -> Option<Options> {
+    let mut options = Options { trace: false, restore: None, script: None };
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--trace" if !options.trace => options.trace = true,
+            "--restore" if options.restore.is_none() => options.restore = Some(rest.next()?.clone()),
+            flag if flag.starts_with("--") => return None,
+            script if options.script.is_none() => options.script = Some(script.to_string()),
+            _ => return None
+        }
+    }
+
+    //a restored session is only useful at the prompt
+    if options.restore.is_some() && options.script.is_some() {
+        return None;
+    }
+    Some(options)
+}
+
+//the engine a script or the prompt runs in. A restored session is loaded
+//first, with trace set only what runs after it is logged to stderr
+fn engine(options: &Options) -> Engine {
+    let mut engine = Engine::new();
+    if let Some(file) = &options.restore {
+        if let Err(err) = engine.load_session(file) {
+            engine.report(&err);
+            process::exit(65);
         }
     }
+    if options.trace {
+        engine.trace();
+    }
+    engine
 }
 
-fn run_file(file: &String) {
-    let contents = fs::read_to_string(file).expect("Error:: Reading the file");
-    let mut intp = Interpreter::default();
-    run(&contents, &mut intp).expect("Execution Error");
+fn run_file(engine: &mut Engine, file: &str) {
+    if let Err(err) = engine.run_file(file) {
+        engine.report(&err);
+        process::exit(65);
+    }
 }
 
-fn run_prompt() {
+//input is collected until it parses, a `...` prompt asks for the rest of
+//an unfinished function, block or string. `:save file` and `:load file`
+//keep the globals across sessions
+fn run_prompt(engine: &mut Engine) {
     let mut lock = io::stdout().lock();
-    let mut intp = Interpreter::default();
+    let mut input = String::new();
 
     loop {
-        write!(lock, ">").unwrap();
+        write!(lock, "{}", if input.is_empty() { ">" } else { "..." }).unwrap();
         io::stdout().flush().unwrap();
 
-        let mut cmd = String::new();
-        io::stdin()
-            .read_line(&mut cmd)
+        let read = io::stdin()
+            .read_line(&mut input)
             .expect("Error reading stdin");
 
-        run(&cmd, &mut intp).unwrap_or(());
+        if input.starts_with(':') {
+            if let Err(err) = repl_command(engine, input.trim()) {
+                engine.report(&err);
+            }
+            input.clear();
+            continue;
+        }
+
+        match engine.eval(&input) {
+            //at the end of stdin there is no rest to wait for
+            Err(err) if read > 0 && is_incomplete(&err.errors) => continue,
+            Err(err) => engine.report(&err),
+            Ok(_) => {}
+        }
+
+        input.clear();
+        if read == 0 {
+            writeln!(lock).unwrap();
+            break;
+        }
+    }
+}
+
+fn repl_command(engine: &mut Engine, command: &str) -> Result<(), lox::engine::Error> {
+    let (name, file) = command.split_once(' ').map_or((command, ""), |(name, file)| (name, file.trim()));
+    match (name, file) {
+        (":save" | ":load", "") => Err(LanguageError::IoError(format!("{} expects a file", name)).into()),
+        (":save", file) => engine.save_session(file),
+        (":load", file) => engine.load_session(file),
+        _ => Err(LanguageError::SyntaxError(format!("Unknown command {}, expected :save or :load", name)).into())
+    }
+}
+
+fn is_incomplete(errs: &[LanguageError]) -> bool {
+    errs.iter().all(|err| matches!(err, LanguageError::IncompleteInput(_)))
+}
+
+fn parse_args(args: &[String]) -> Result<Command, String> {
+    let stage = args[0].clone();
+    let mut source: Option<String> = None;
+    let mut format = Format::Text;
+    let mut scopes = false;
+
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        let input = match arg.as_str() {
+            "--format=text" => {
+                format = Format::Text;
+                continue;
+            },
+            "--format=json" => {
+                format = Format::Json;
+                continue;
+            },
+            "--format=sexp" => {
+                format = Format::Sexp;
+                continue;
+            },
+            "--format=dot" => {
+                format = Format::Dot;
+                continue;
+            },
+            "--scopes" => {
+                scopes = true;
+                continue;
+            },
+            "-e" => rest.next().cloned().ok_or("-e expects code to run")?,
+            "-" => {
+                let mut input = String::new();
+                io::stdin().read_to_string(&mut input).map_err(|e| e.to_string())?;
+                input
+            },
+            flag if flag.starts_with('-') => return Err(format!("Unknown option {}", flag)),
+            file => fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?
+        };
+
+        if source.replace(input).is_some() {
+            return Err("Expected a single source".into());
+        }
+    }
+
+    if format == Format::Dot && stage != "ast" {
+        return Err("--format=dot only applies to ast".into());
+    }
+    if scopes && format != Format::Dot {
+        return Err("--scopes only applies to --format=dot".into());
+    }
+
+    match source {
+        Some(source) => Ok(Command { stage, source, format, scopes }),
+        None => Err("Expected a file, - or -e code".into())
+    }
+}
+
+//formats files in place, or stdin to stdout. With --check nothing is
+//written and the files that aren't formatted are listed
+fn run_fmt(args: &[String]) -> Result<i32, String> {
+    let mut check = false;
+    let mut width = 80;
+    let mut files: Vec<&str> = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            flag if flag.starts_with("--width=") => {
+                width = flag["--width=".len()..].parse().map_err(|_| format!("Invalid width {}", flag))?;
+            },
+            "-" => files.push("-"),
+            flag if flag.starts_with('-') => return Err(format!("Unknown option {}", flag)),
+            file => files.push(file)
+        }
+    }
+    if files.is_empty() {
+        return Err("Expected files to format or -".into());
+    }
+
+    let mut status = 0;
+    for file in files {
+        let source = if file == "-" {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input).map_err(|e| e.to_string())?;
+            input
+        }else{
+            fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?
+        };
+
+        match lox::format::format(&source, width) {
+            Ok(formatted) if check => {
+                if formatted != source {
+                    println!("{}", file);
+                    status = status.max(1);
+                }
+            },
+            Ok(formatted) if file == "-" => print!("{}", formatted),
+            Ok(formatted) => {
+                if formatted != source {
+                    fs::write(file, formatted).map_err(|e| format!("{}: {}", file, e))?;
+                }
+            },
+            Err(errs) => {
+                eprintln!("{}:", file);
+                report(&errs, Format::Text);
+                status = 65;
+            }
+        }
+    }
+
+    Ok(status)
+}
+
+fn run_command(cmd: Command) -> Result<(), Vec<LanguageError>> {
+    match (cmd.stage.as_str(), cmd.format) {
+        ("tokens", Format::Text) => {
+            for token in scan(&cmd.source)? {
+                println!("{:>4} {:<14} {}", token.line, lox::json::token_type_name(&token.token_type), token.lexeme);
+            }
+        },
+        ("tokens", format) => {
+            let tokens = scan(&cmd.source)?;
+            emit(&Json::Array(tokens.iter().map(Json::from).collect()), format);
+        },
+        ("ast", Format::Text) => {
+            let (ast, stmts) = parse(&cmd.source)?;
+            let mut printer = AstPrinter::new(&ast);
+            for stmt in stmts {
+                println!("{}", printer.print_stmt(stmt));
+            }
+        },
+        ("ast", Format::Dot) => {
+            let (ast, stmts) = if cmd.scopes { resolve(&cmd.source)? } else { parse(&cmd.source)? };
+            print!("{}", lox::dot::program(&ast, &stmts, cmd.scopes));
+        },
+        ("ast", format) => {
+            let (ast, stmts) = parse(&cmd.source)?;
+            emit(&lox::json::program(&ast, &stmts), format);
+        },
+        ("check", format) => {
+            resolve(&cmd.source)?;
+            match format {
+                Format::Text => println!("ok"),
+                _ => emit(&Json::object(vec![("errors", Json::Array(Vec::new()))]), format)
+            }
+        },
+        ("run", _) => {
+            Engine::new().eval(&cmd.source).map_err(|err| err.errors)?;
+        },
+        ("load", _) => {
+            let (ast, stmts) = load(&cmd.source)?;
+            Engine::new().run(ast, &stmts).map_err(|err| err.errors)?;
+        },
+        _ => unreachable!()
     }
+
+    Ok(())
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() > 2 {
-        println!("Usage: rlox [script]");
-    } else if args.len() == 2 {
-        run_file(&args[1]);
-    } else {
-        run_prompt();
+    match args.get(1).map(String::as_str) {
+        Some("tokens" | "ast" | "check" | "run" | "load") => {
+            let cmd = match parse_args(&args[1..]) {
+                Ok(cmd) => cmd,
+                Err(err) => {
+                    eprintln!("{}\n{}", err, USAGE);
+                    process::exit(64);
+                }
+            };
+
+            let format = cmd.format;
+            if let Err(errs) = run_command(cmd) {
+                report(&errs, format);
+                process::exit(65);
+            }
+        },
+        Some("fmt") => match run_fmt(&args[2..]) {
+            Ok(status) => process::exit(status),
+            Err(err) => {
+                eprintln!("{}\n{}", err, USAGE);
+                process::exit(64);
+            }
+        },
+        _ => match parse_options(&args[1..]) {
+            Some(options) => {
+                let mut engine = engine(&options);
+                match &options.script {
+                    Some(file) => run_file(&mut engine, file),
+                    None => run_prompt(&mut engine)
+                }
+            },
+            None => {
+                eprintln!("{}", USAGE);
+                process::exit(64);
+            }
+        }
     }
 }
@@ -0,0 +1,42 @@
+extern crate lox_rs;
+
+use lox_rs::lox::ast::Stmt;
+use lox_rs::lox::atom::Atom;
+use lox_rs::lox::engine::{self, Engine};
+
+#[test]
+fn doc_comments_attach_to_declarations() {
+    let (ast, stmts) = engine::parse("/// the answer\n/// to everything\nvar x = 42;\n{\n    /// inner\n    fun f() {}\n}").unwrap();
+
+    match ast.stmt(stmts[0]) {
+        Stmt::Var(_, _, doc) => assert_eq!(doc.as_deref(), Some("the answer\nto everything")),
+        other => panic!("expected a var, got {:?}", other)
+    }
+    let inner = match ast.stmt(stmts[1]) {
+        Stmt::Block(stmts) => ast.stmt(stmts[0]),
+        other => panic!("expected a block, got {:?}", other)
+    };
+    match inner {
+        Stmt::Function(_, doc) => assert_eq!(doc.as_deref(), Some("inner")),
+        other => panic!("expected a function, got {:?}", other)
+    }
+}
+
+#[test]
+fn other_doc_comments_are_plain_comments() {
+    let mut engine = Engine::new();
+    let source = "fun add(a, b) { return a + b; }
+/// not a declaration
+print add(1, /// between arguments
+    2);
+var inc = (n /// in parameters
+) => n + 1;
+/// at the end of a block
+{ inc(1); /// before the brace
+}
+add(inc(1), 3) /// before the semicolon
+;
+/// at the end";
+
+    assert!(engine.eval(source).unwrap() == Atom::Number(5.0));
+}
@@ -0,0 +1,93 @@
+extern crate lox_rs;
+
+use lox_rs::lox::document::{Declaration, Document};
+use lox_rs::lox::json;
+use lox_rs::lox::scanner::Token;
+use lox_rs::lox::testing::Rng;
+use std::rc::Rc;
+
+const SOURCE: &str = "/// the first\nvar a = 1;\nprint a + 2; // note\n/* block\n comment */ var b = \"s\";\nprint (a - 3) * b;\n";
+
+const INSERTS: &[&str] = &["", "x", " ", "\n", ";", "var c = 4;", "\"", "/*", "*/", "// c\n", "(", ")", "1", "+"];
+
+//spans inside a declaration that moved are where it was parsed, so only
+//its own span is compared
+fn render(decl: &Declaration) -> String {
+    format!("{} {:?} {:?} {:?}", json::shape(&json::stmt(&decl.ast, decl.stmt)), decl.span(), decl.tokens, decl.errors)
+}
+
+//an incrementally updated document has to match one built from scratch
+fn assert_same(doc: &Document) {
+    let fresh = Document::new(doc.source().into());
+
+    let tokens: Vec<String> = doc.tokens().iter().enumerate().map(|(i, t)| format!("{} {:?}", doc.line(i), Token { line: 0, ..(**t).clone() })).collect();
+    let fresh_tokens: Vec<String> = fresh.tokens().iter().map(|t| format!("{} {:?}", t.line, Token { line: 0, ..(**t).clone() })).collect();
+    assert_eq!(tokens, fresh_tokens, "tokens differ for {:?}", doc.source());
+
+    let decls: Vec<String> = doc.declarations().iter().map(render).collect();
+    let fresh_decls: Vec<String> = fresh.declarations().iter().map(render).collect();
+    assert_eq!(decls, fresh_decls, "declarations differ for {:?}", doc.source());
+}
+
+#[test]
+fn random_edits_match_full_reparse() {
+    let mut rng = Rng::new(0x9e37_79b9_7f4a_7c15);
+
+    for _ in 0..200 {
+        let mut doc = Document::new(SOURCE.into());
+        for _ in 0..10 {
+            let len = doc.source().len();
+            let start = rng.below(len + 1);
+            let end = (start + rng.below(4)).min(len);
+            doc.apply_edit(start..end, INSERTS[rng.below(INSERTS.len())]).unwrap();
+            assert_same(&doc);
+        }
+    }
+}
+
+#[test]
+fn untouched_declarations_keep_identity() {
+    let mut doc = Document::new("var a = 1;\nvar b = 2;\nvar c = 3;\n".into());
+    let before: Vec<Declaration> = doc.declarations().to_vec();
+    let first_token = doc.tokens()[0].clone();
+
+    //1 -> 42 inside `var b = 2;`
+    doc.apply_edit(19..20, "42").unwrap();
+    assert_same(&doc);
+
+    let same = |a: &Declaration, b: &Declaration| Rc::ptr_eq(&a.ast, &b.ast) && a.stmt == b.stmt;
+    let after = doc.declarations();
+    assert!(same(&before[0], &after[0]));
+    assert!(!same(&before[1], &after[1]));
+    assert!(same(&before[2], &after[2]));
+    assert!(Rc::ptr_eq(&first_token, &doc.tokens()[0]));
+}
+
+#[test]
+fn declarations_after_a_new_line_are_moved_not_reparsed() {
+    let mut doc = Document::new("var a = 1;\nvar b = 2;\nvar c = 3;\n".into());
+    let before: Vec<Declaration> = doc.declarations().to_vec();
+    let last_token = doc.tokens()[doc.tokens().len() - 2].clone();
+
+    //a line break inside `var a = 1;`
+    doc.apply_edit(7..7, "\n").unwrap();
+    assert_same(&doc);
+
+    let after = doc.declarations();
+    assert!(Rc::ptr_eq(&before[2].ast, &after[2].ast) && before[2].stmt == after[2].stmt);
+    assert_eq!(after[2].span().line, 4);
+    assert!(Rc::ptr_eq(&last_token, &doc.tokens()[doc.tokens().len() - 2]));
+    assert_eq!(doc.line(doc.tokens().len() - 2), 4);
+}
+
+#[test]
+fn bad_edit_ranges_are_errors() {
+    let mut doc = Document::new("print \"é\";".into());
+    assert!(doc.apply_edit(8..20, "").is_err());
+    let (start, end) = (4, 2);
+    assert!(doc.apply_edit(start..end, "").is_err());
+    //inside the two bytes of é
+    assert!(doc.apply_edit(8..8, "x").is_err());
+    assert_eq!(doc.source(), "print \"é\";");
+    assert_same(&doc);
+}
@@ -0,0 +1,35 @@
+extern crate lox_rs;
+
+use lox_rs::lox::dot;
+use lox_rs::lox::engine;
+use lox_rs::lox::resolver;
+
+#[test]
+fn nodes_tokens_and_literals() {
+    let (ast, stmts) = engine::parse("print 1 + \"two\";").unwrap();
+    let graph = dot::program(&ast, &stmts, false);
+
+    assert!(graph.starts_with("digraph ast {\n"));
+    assert!(graph.ends_with("}\n"));
+    assert!(graph.contains("n2 [label=\"Print\", shape=box];"));
+    assert!(graph.contains("n3 [label=\"Binary\", shape=box];"));
+    assert!(graph.contains("n4 [label=\"Literal\\n1\", shape=box];"));
+    assert!(graph.contains("n5 [label=\"+\", shape=plaintext];"));
+    assert!(graph.contains("n6 [label=\"Literal\\n\\\"two\\\"\", shape=box];"));
+    assert!(graph.contains("n3 -> n5 [label=\"operator\"];"));
+    assert!(!graph.contains("subgraph"));
+}
+
+#[test]
+fn scopes_link_variables_to_declarations() {
+    let (mut ast, stmts) = engine::parse("var g = 1; fun f(a) { { var b = a; return b + g; } }").unwrap();
+    resolver::resolve(&mut ast, &stmts).unwrap();
+    let graph = dot::program(&ast, &stmts, true);
+
+    assert_eq!(graph.matches("subgraph cluster_").count(), 2);
+    assert!(graph.contains("[label=\"param a\", shape=ellipse];"));
+    assert!(graph.contains("[label=\"Var a\\ndepth 1\", shape=box];"));
+    assert!(graph.contains("[label=\"Var b\\ndepth 0\", shape=box];"));
+    assert!(graph.contains("[label=\"Var g\\nglobal\", shape=box];"));
+    assert_eq!(graph.matches("style=dashed, color=blue").count(), 2);
+}
@@ -0,0 +1,87 @@
+extern crate lox_rs;
+
+use lox_rs::lox::atom::Atom;
+use lox_rs::lox::engine::{self, Engine};
+use lox_rs::lox::errors::LanguageError;
+
+use std::{env, fs};
+
+#[test]
+fn eval_returns_the_last_expression() {
+    let mut engine = Engine::new();
+    assert!(engine.eval("var a = 20; a * 2 + 2;").unwrap() == Atom::Number(42.0));
+    assert!(engine.eval("var b = 1;").unwrap() == Atom::Nil);
+    //globals carry over between evals
+    assert!(engine.eval("a + b;").unwrap() == Atom::Number(21.0));
+}
+
+#[test]
+fn globals_from_the_host() {
+    let mut engine = Engine::new();
+    engine.set_global("limit", Atom::Number(10.0));
+    engine.eval("var over = limit > 5;").unwrap();
+
+    assert!(engine.get_global("over") == Some(Atom::Bool(true)));
+    assert!(engine.get_global("missing").is_none());
+}
+
+#[test]
+fn call_a_lox_function() {
+    let mut engine = Engine::new();
+    engine.eval("fun discount(price, rate) { return price - price * rate; }").unwrap();
+
+    let discount = engine.get_global("discount").unwrap();
+    let value = engine.call(&discount, vec![Atom::Number(200.0), Atom::Number(0.25)]).unwrap();
+    assert!(value == Atom::Number(150.0));
+
+    let err = engine.call(&discount, vec![Atom::Number(1.0)]).unwrap_err();
+    assert!(matches!(err.errors.as_slice(), [LanguageError::RuntimeError(_)]));
+    let err = engine.call(&Atom::Number(1.0), Vec::new()).unwrap_err();
+    assert!(matches!(err.errors.as_slice(), [LanguageError::TypeError(_)]));
+}
+
+#[test]
+fn every_phase_reports_the_same_error_type() {
+    let mut engine = Engine::new();
+    let kinds = |source: &str, engine: &mut Engine| -> Vec<&'static str> {
+        engine.eval(source).unwrap_err().errors.iter().map(|err| err.kind()).collect()
+    };
+
+    assert_eq!(kinds("var s = \"open", &mut engine), ["IncompleteInput"]);
+    assert_eq!(kinds("print );", &mut engine), ["ParserError"]);
+    assert_eq!(kinds("{ var a = a; }", &mut engine), ["ResolveError"]);
+    assert_eq!(kinds("print -\"s\";", &mut engine), ["TypeError"]);
+    assert_eq!(kinds("print nope;", &mut engine), ["RuntimeError"]);
+
+    let err = engine.eval("nope;").unwrap_err();
+    assert_eq!(err.to_string(), "RuntimeError: Undefined variable nope");
+
+    //an error doesn't leave the engine unusable
+    assert!(engine.eval("1 + 1;").unwrap() == Atom::Number(2.0));
+}
+
+#[test]
+fn run_file_reads_and_runs() {
+    let path = env::temp_dir().join(format!("lox-engine-{}.lox", std::process::id()));
+    fs::write(&path, "fun double(x) { return x * 2; }\ndouble(21);\n").unwrap();
+
+    let mut engine = Engine::new();
+    let value = engine.run_file(&path);
+    fs::remove_file(&path).unwrap();
+    assert!(value.unwrap() == Atom::Number(42.0));
+
+    let err = engine.run_file(path).unwrap_err();
+    assert!(matches!(err.errors.as_slice(), [LanguageError::IoError(_)]));
+}
+
+#[test]
+fn a_missing_operand_is_reported_once() {
+    let errors = |source: &str| -> Vec<String> {
+        engine::parse(source).err().unwrap().errors.iter().map(|err| err.to_string()).collect()
+    };
+
+    //the `;` is left for the statement, nothing after it is reported
+    assert_eq!(errors("print 1 +;\nprint 2;"), ["ParserError: Expected expression, found SEMICOLON"]);
+    assert_eq!(errors(";"), ["ParserError: Expected expression, found SEMICOLON"]);
+    assert_eq!(errors("{ print -; }"), ["ParserError: Expected expression, found SEMICOLON"]);
+}
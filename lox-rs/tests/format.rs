@@ -0,0 +1,141 @@
+extern crate lox_rs;
+
+use lox_rs::lox::format::{self, format};
+use lox_rs::lox::{json, sexp};
+
+const MESSY: &str = "// header
+
+
+/// adds things
+fun add(a,b){
+return a+b;}
+var   x=1;var y = nil;
+/* block
+   comment */
+var twice = fun (x) { return x*2; };
+var g = (a, b) => {
+  print a;
+
+  print b; // after b
+};
+{
+}
+print add(twice(3),x) ~/ 2 ** 3;
+";
+
+const FORMATTED: &str = "// header
+
+/// adds things
+fun add(a, b) {
+    return a + b;
+}
+var x = 1;
+var y;
+/* block
+   comment */
+var twice = (x) => x * 2;
+var g = fun (a, b) {
+    print a;
+
+    print b; // after b
+};
+{}
+print add(twice(3), x) ~/ 2 ** 3;
+";
+
+#[test]
+fn formats_to_canonical_layout() {
+    assert_eq!(format(MESSY, 80).unwrap(), FORMATTED);
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    assert_eq!(format(FORMATTED, 80).unwrap(), FORMATTED);
+}
+
+#[test]
+fn wraps_at_width() {
+    let source = "print some_function(first_argument, second_argument) + another_value;\n";
+    assert_eq!(format(source, 80).unwrap(), source);
+    assert_eq!(format(source, 40).unwrap(), "print some_function(
+    first_argument,
+    second_argument
+) +
+    another_value;
+");
+}
+
+#[test]
+fn lambdas_keep_their_meaning() {
+    //an arrow here would take `(2)` as part of its body
+    let source = "print fun (x) { return x; }(2);\nprint f((x) => x, 1);\n";
+    assert_eq!(format(source, 80).unwrap(), "print fun (x) {\n    return x;\n}(2);\nprint f((x) => x, 1);\n");
+}
+
+#[test]
+fn comments_after_a_closing_brace_stay_there() {
+    let source = "fun f() { return 1; } // after\n{ print 1;\n  print 2; } // block\n";
+    let formatted = "fun f() {\n    return 1;\n} // after\n{\n    print 1;\n    print 2;\n} // block\n";
+    assert_eq!(format(source, 80).unwrap(), formatted);
+    assert_eq!(format(formatted, 80).unwrap(), formatted);
+}
+
+#[test]
+fn long_files_format() {
+    let source = "var x = 1;\n".repeat(20000);
+    assert_eq!(format(&source, 80).unwrap(), source);
+}
+
+#[test]
+fn refuses_broken_source() {
+    assert!(format("var = 1;", 80).is_err());
+}
+
+//a tree with no Grouping nodes, as a loaded or folded one may be
+fn written(expression: &str) -> Result<String, String> {
+    let program = format!("((Print :expression {}))", expression);
+    let (ast, stmts) = json::load(&sexp::parse(&program).unwrap()).unwrap();
+    format::program(&ast, &stmts, 80).map_err(|err| err.to_string())
+}
+
+fn binary(lhs: &str, op: &str, lexeme: &str, rhs: &str) -> String {
+    format!("(Binary :left {} :operator ({} :lexeme \"{}\") :right {})", lhs, op, lexeme, rhs)
+}
+
+fn number(n: &str) -> String {
+    format!("(Literal :value {})", n)
+}
+
+#[test]
+fn parenthesizes_what_binds_looser() {
+    let sum = binary(&number("1"), "PLUS", "+", &number("2"));
+    assert_eq!(written(&binary(&sum, "STAR", "*", &number("3"))).unwrap(), "print (1 + 2) * 3;\n");
+    assert_eq!(written(&binary(&number("3"), "MINUS", "-", &sum)).unwrap(), "print 3 - (1 + 2);\n");
+    assert_eq!(written(&binary(&sum, "PLUS", "+", &number("3"))).unwrap(), "print 1 + 2 + 3;\n");
+
+    let power = binary(&number("2"), "STARSTAR", "**", &number("3"));
+    assert_eq!(written(&binary(&power, "STARSTAR", "**", &number("2"))).unwrap(), "print (2 ** 3) ** 2;\n");
+    assert_eq!(written(&binary(&number("2"), "STARSTAR", "**", &power)).unwrap(), "print 2 ** 2 ** 3;\n");
+
+    let negated = "(Unary :operator (MINUS :lexeme \"-\") :right (Var :name \"x\"))";
+    assert_eq!(written(&binary(negated, "STARSTAR", "**", &number("2"))).unwrap(), "print (-x) ** 2;\n");
+    assert_eq!(written(&binary(&number("-2"), "STARSTAR", "**", &number("2"))).unwrap(), "print (-2) ** 2;\n");
+
+    let pair = binary(&number("1"), "COMMA", ",", &number("2"));
+    let call = format!("(Call :callee (Var :name \"f\") :paren (RIGHTPAREN :lexeme \")\") :arguments ({} {}))", pair, number("3"));
+    assert_eq!(written(&call).unwrap(), "print f((1, 2), 3);\n");
+}
+
+#[test]
+fn source_keeps_its_own_parentheses() {
+    let source = "print 2 ** -x * (1 + 2);\nprint -x ** 2;\nprint (-x) ** 2;\n";
+    assert_eq!(format(source, 80).unwrap(), source);
+}
+
+#[test]
+fn literals_without_a_literal_form() {
+    assert_eq!(written("(Literal :value (:number \"Infinity\"))").unwrap(), "print (1 / 0);\n");
+    assert_eq!(written("(Literal :value (:number \"NaN\"))").unwrap(), "print (0 / 0);\n");
+    assert_eq!(written("(Literal :value \"say \\\"hi\\\"\")").unwrap_err(),
+               "SyntaxError: A string containing \" can't be written as Lox source");
+}
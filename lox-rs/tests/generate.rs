@@ -0,0 +1,28 @@
+extern crate lox_rs;
+
+use lox_rs::lox::ast::Ast;
+use lox_rs::lox::format;
+use lox_rs::lox::testing::{self, Generator};
+
+#[test]
+fn generated_programs_parse_back() {
+    for seed in 0..500 {
+        //narrow widths force the wrapped layouts too
+        let width = [20, 40, 80][seed as usize % 3];
+        if let Err(err) = testing::round_trip(seed, width) {
+            panic!("{}", err);
+        }
+    }
+}
+
+#[test]
+fn same_seed_same_program() {
+    let print = |seed| {
+        let mut ast = Ast::default();
+        let stmts = Generator::new(seed).program(&mut ast);
+        format::program(&ast, &stmts, 80).unwrap()
+    };
+
+    assert_eq!(print(7), print(7));
+    assert_ne!(print(7), print(8));
+}
@@ -0,0 +1,145 @@
+extern crate lox_rs;
+
+use lox_rs::lox::atom::Atom;
+use lox_rs::lox::engine::Engine;
+use lox_rs::lox::errors::LanguageError;
+use lox_rs::lox::host::HostObject;
+use lox_rs::lox::native::IntoAtom;
+use lox_rs::lox::output::Buffer;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+struct Request {
+    path: String,
+    status: f64,
+    headers: HashMap<String, String>
+}
+
+impl HostObject for Request {
+    fn type_name(&self) -> &'static str {
+        "Request"
+    }
+
+    fn get(&self, name: &str) -> Option<Atom> {
+        match name {
+            "path" => Some(Atom::String(self.path.clone())),
+            "status" => Some(Atom::Number(self.status)),
+            _ => None
+        }
+    }
+
+    fn set(&mut self, name: &str, value: Atom) -> Result<(), LanguageError> {
+        match (name, value) {
+            ("status", Atom::Number(status)) => {
+                self.status = status;
+                Ok(())
+            },
+            ("status", other) => Err(LanguageError::TypeError(format!("status must be a Number, not {}", other.type_name()))),
+            _ => Err(LanguageError::RuntimeError(format!("Request.{} is read only", name)))
+        }
+    }
+
+    fn has_method(&self, name: &str) -> bool {
+        matches!(name, "header" | "set_header")
+    }
+
+    fn call_method(&mut self, name: &str, args: Vec<Atom>) -> Result<Atom, LanguageError> {
+        match (name, args.as_slice()) {
+            ("header", [Atom::String(key)]) => Ok(self.headers.get(key).cloned().into_atom()),
+            ("set_header", [Atom::String(key), Atom::String(value)]) => {
+                self.headers.insert(key.clone(), value.clone());
+                Ok(Atom::Nil)
+            },
+            _ => Err(LanguageError::RuntimeError(format!("Bad arguments to Request.{}", name)))
+        }
+    }
+}
+
+fn request() -> Rc<RefCell<Request>> {
+    let headers = HashMap::from([("accept".to_string(), "text/html".to_string())]);
+    Rc::new(RefCell::new(Request { path: "/index".into(), status: 200.0, headers }))
+}
+
+fn engine_with(request: &Rc<RefCell<Request>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_global("req", request.clone().into_atom());
+    engine
+}
+
+#[test]
+fn properties_and_methods_through_dot() {
+    let req = request();
+    let mut engine = engine_with(&req);
+
+    assert!(engine.eval("req.path;").unwrap() == Atom::String("/index".into()));
+    assert!(engine.eval("req.header(\"accept\");").unwrap() == Atom::String("text/html".into()));
+    assert!(engine.eval("req.header(\"missing\");").unwrap() == Atom::Nil);
+    //a method is a value bound to its object
+    assert!(engine.eval("var h = req.header; h(\"accept\");").unwrap() == Atom::String("text/html".into()));
+}
+
+#[test]
+fn changes_are_shared_with_the_host() {
+    let req = request();
+    let mut engine = engine_with(&req);
+
+    assert!(engine.eval("req.status = 404;").unwrap() == Atom::Number(404.0));
+    assert_eq!(req.borrow().status, 404.0);
+    engine.eval("req.status += 1; req.set_header(\"x-lox\", \"yes\");").unwrap();
+    assert_eq!(req.borrow().status, 405.0);
+    assert_eq!(req.borrow().headers["x-lox"], "yes");
+
+    req.borrow_mut().path = String::from("/changed");
+    assert!(engine.eval("req.path;").unwrap() == Atom::String("/changed".into()));
+
+    //a script keeps the object alive as long as it holds on to it
+    engine.eval("var kept = req;").unwrap();
+    assert_eq!(Rc::strong_count(&req), 3);
+}
+
+#[test]
+fn objects_are_values() {
+    let req = request();
+    let mut engine = engine_with(&req);
+    let out = Buffer::new();
+    engine.set_stdout(out.clone());
+    engine.register("new_request", |path: String| {
+        let req = request();
+        req.borrow_mut().path = path;
+        req
+    }).unwrap();
+
+    engine.eval("print req; print typeof(req); print req == req; print req == new_request(\"/\");").unwrap();
+    assert_eq!(out.contents(), "<instance Request>\nRequest\ntrue\nfalse\n");
+    assert!(engine.eval("new_request(\"/other\").path;").unwrap() == Atom::String("/other".into()));
+}
+
+#[test]
+fn misuse_is_an_error() {
+    let req = request();
+    let mut engine = engine_with(&req);
+    let error = |engine: &mut Engine, source: &str| engine.eval(source).unwrap_err().to_string();
+
+    assert_eq!(error(&mut engine, "req.nope;"), "RuntimeError: Undefined property nope on Request");
+    assert_eq!(error(&mut engine, "req.path = \"/\";"), "RuntimeError: Request.path is read only");
+    assert_eq!(error(&mut engine, "req.status = \"ok\";"), "TypeError: status must be a Number, not String");
+    assert_eq!(error(&mut engine, "req.header(1);"), "RuntimeError: Bad arguments to Request.header");
+    assert_eq!(error(&mut engine, "\"s\".length;"), "TypeError: String has no properties");
+    assert_eq!(error(&mut engine, "nil.x = 1;"), "TypeError: Nil has no properties");
+    assert_eq!(req.borrow().path, "/index");
+}
+
+#[test]
+fn dot_syntax_round_trips() {
+    use lox_rs::lox::{engine, format, json};
+
+    let (ast, stmts) = engine::parse("a.b.c(1).d = e.f += 2;").unwrap();
+    assert_eq!(format::program(&ast, &stmts, 80).unwrap(), "a.b.c(1).d = e.f += 2;\n");
+    let loaded = json::load(&json::program(&ast, &stmts)).unwrap();
+    assert_eq!(json::shape(&json::program(&loaded.0, &loaded.1)), json::shape(&json::program(&ast, &stmts)));
+
+    assert!(engine::parse("a.1;").is_err());
+    assert!(engine::parse("a.b() = 1;").is_err());
+}
@@ -0,0 +1,118 @@
+extern crate lox_rs;
+
+use lox_rs::lox::ast::{Ast, Interpreter};
+use lox_rs::lox::atom::Atom;
+use lox_rs::lox::engine;
+use lox_rs::lox::errors::LanguageError;
+use lox_rs::lox::parser::{Parser, MAX_DEPTH};
+use lox_rs::lox::resolver;
+use lox_rs::lox::scanner::Scanner;
+use lox_rs::lox::symbol::Symbol;
+
+use std::rc::Rc;
+use std::thread;
+
+fn run(source: &str, max_depth: usize) -> (Interpreter, Option<LanguageError>) {
+    let (mut ast, stmts) = engine::parse(source).unwrap();
+    resolver::resolve(&mut ast, &stmts).unwrap();
+    let mut intp = Interpreter::default();
+    intp.max_depth = max_depth;
+    intp.interpret(&Rc::new(ast), &stmts);
+    let err = intp.error.take();
+    (intp, err)
+}
+
+fn assert_too_deep(source: &str) {
+    match engine::parse(source).map(|_| ()).unwrap_err().errors.as_slice() {
+        [LanguageError::ParserError(err)] => assert_eq!(err, &format!("Nesting deeper than {} levels", MAX_DEPTH)),
+        errs => panic!("expected a single nesting error, got {:?}", errs)
+    }
+}
+
+#[test]
+fn hostile_nesting_is_a_parse_error() {
+    let parens = format!("print {}1{};", "(".repeat(100_000), ")".repeat(100_000));
+    assert_too_deep(&parens);
+
+    let unclosed = "(".repeat(100_000);
+    assert_too_deep(&unclosed);
+
+    let negations = format!("print {}1;", "-".repeat(100_000));
+    assert_too_deep(&negations);
+
+    let blocks = "{".repeat(100_000);
+    assert_too_deep(&blocks);
+
+    let lambdas = "fun () {".repeat(100_000);
+    assert_too_deep(&lambdas);
+}
+
+#[test]
+fn long_chains_are_a_parse_error() {
+    //parsed in a loop rather than by recursion, but every pass after the
+    //parser would still recurse once per link
+    let sums = format!("print 1{};", "+1".repeat(100_000));
+    assert_too_deep(&sums);
+
+    let commas = format!("print 1{};", ",1".repeat(100_000));
+    assert_too_deep(&commas);
+
+    let calls = format!("print f{};", "()".repeat(100_000));
+    assert_too_deep(&calls);
+
+    let properties = format!("print a{};", ".b".repeat(100_000));
+    assert_too_deep(&properties);
+
+    //a chain inside a function body is as deep as the function
+    let nested = format!("fun f() {{ {} print 1{}; {} }}", "{".repeat(150), "+1".repeat(150), "}".repeat(150));
+    assert_too_deep(&nested);
+}
+
+#[test]
+fn chains_up_to_the_limit_run() {
+    let source = format!("var x = 0{};", "+1".repeat(200));
+    let (intp, err) = run(&source, 1000);
+    assert!(err.is_none(), "{:?}", err);
+    assert!(intp.globals.borrow().get(&Symbol::intern("x")) == Atom::Number(200.0));
+}
+
+#[test]
+fn nesting_up_to_the_limit_runs() {
+    let source = format!("var x = {}1{};", "-(".repeat(100), ")".repeat(100));
+    let (intp, err) = run(&source, 1000);
+    assert!(err.is_none(), "{:?}", err);
+    assert!(intp.globals.borrow().get(&Symbol::intern("x")) == Atom::Number(1.0));
+
+    let tokens = Scanner::new(source).scan_tokens().unwrap().clone();
+    assert!(Parser::new(&tokens, &mut Ast::default()).with_max_depth(200).parse().is_err());
+}
+
+#[test]
+fn runaway_recursion_is_a_runtime_error() {
+    let (_, err) = run("fun f(n) { return f(n + 1); } f(0);", 300);
+    assert!(matches!(err, Some(LanguageError::RuntimeError(msg)) if msg == "Nesting deeper than 300 levels"));
+}
+
+#[test]
+fn default_limit_fits_the_main_thread() {
+    //the main thread of a program gets 8MB of stack
+    let handle = thread::Builder::new().stack_size(8 << 20).spawn(|| {
+        let source = "fun f(n) { return n < 1 ? 0 : 1 + f(n - 1); } var result = f(1000000);";
+        let (_, err) = run(source, Interpreter::default().max_depth);
+        assert!(matches!(err, Some(LanguageError::RuntimeError(_))), "{:?}", err);
+    }).unwrap();
+    handle.join().unwrap();
+}
+
+#[test]
+fn unary_operators() {
+    let (intp, err) = run("var a = -2; var b = !nil; var c = --a;", 1000);
+    assert!(err.is_none(), "{:?}", err);
+    let globals = intp.globals.borrow();
+    assert!(globals.get(&Symbol::intern("a")) == Atom::Number(-2.0));
+    assert!(globals.get(&Symbol::intern("b")) == Atom::Bool(true));
+    assert!(globals.get(&Symbol::intern("c")) == Atom::Number(-2.0));
+
+    let (_, err) = run("var s = -\"text\";", 1000);
+    assert!(matches!(err, Some(LanguageError::TypeError(_))));
+}
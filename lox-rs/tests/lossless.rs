@@ -0,0 +1,68 @@
+extern crate lox_rs;
+
+use lox_rs::lox::scanner::{Scanner, Token};
+use lox_rs::lox::testing::Rng;
+
+const FRAGMENTS: &[&str] = &[
+    "var", "print", "x", "foo_bar", "nil", "true", "and", "42", "3.25", "\"str\"", "\"multi\nline\"",
+    "(", ")", "{", "}", ",", ".", "-", "+", ";", "*", "/", "!", "!=", "=", "==", ">", ">=", "<", "<=",
+    " ", "  ", "\t", "\r\n", "\n", "\n\n", "// line comment\n", "/// doc comment\n",
+    "/* block */", "/* multi\nline */", "/* outer /* inner */ outer */",
+    "\"é\"", "\"日本\"", "naïve", "// é\n", "/// ünïcode\n", "/* ∀ /* λ */ */", "\"🦀\"",
+];
+
+fn random_source(rng: &mut Rng) -> String {
+    let len = rng.below(40);
+    let mut source = String::new();
+    for _ in 0..len {
+        source.push_str(FRAGMENTS[rng.below(FRAGMENTS.len())]);
+        //mix glued and spaced fragments, both must round trip
+        if rng.below(2) == 0 {
+            source.push(' ');
+        }
+    }
+    source
+}
+
+fn concat(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|t| format!("{}{}{}", t.leading_trivia, t.lexeme, t.trailing_trivia))
+        .collect()
+}
+
+#[test]
+fn lossless_tokens_round_trip() {
+    let mut rng = Rng::new(0x2545_f491_4f6c_dd1d);
+    let mut checked = 0;
+
+    for _ in 0..2000 {
+        let source = random_source(&mut rng);
+        let mut scanner = Scanner::lossless(source.clone());
+        if let Ok(tokens) = scanner.scan_tokens() {
+            assert_eq!(concat(tokens), source, "round trip failed for {:?}", source);
+            checked += 1;
+        }
+    }
+
+    assert!(checked > 1000);
+}
+
+#[test]
+fn non_ascii_source_round_trips() {
+    for source in ["print \"é\";", "a // é\n", "var naïve = \"日本\"; /* ∀ */\n", "/// 🦀\nvar x;"] {
+        let mut scanner = Scanner::lossless(source.into());
+        let tokens = scanner.scan_tokens().unwrap();
+        assert_eq!(concat(tokens), source);
+    }
+}
+
+#[test]
+fn trailing_trivia_ends_at_line_break() {
+    let mut scanner = Scanner::lossless("var x; // comment\n  print x;\n".into());
+    let tokens = scanner.scan_tokens().unwrap();
+
+    assert_eq!(tokens[2].trailing_trivia, " // comment\n");
+    assert_eq!(tokens[3].leading_trivia, "  ");
+    assert_eq!(tokens.last().unwrap().leading_trivia, "");
+}
@@ -0,0 +1,82 @@
+extern crate lox_rs;
+
+use lox_rs::lox::atom::Atom;
+use lox_rs::lox::engine::Engine;
+
+fn error(engine: &mut Engine, source: &str) -> String {
+    engine.eval(source).unwrap_err().to_string()
+}
+
+#[test]
+fn clock_is_defined() {
+    let mut engine = Engine::new();
+    let before = engine.eval("clock();").unwrap();
+    let after = engine.eval("clock();").unwrap();
+    match (before, after) {
+        (Atom::Number(before), Atom::Number(after)) => assert!(before > 0.0 && after >= before),
+        other => panic!("clock returned {:?}", other)
+    }
+}
+
+#[test]
+fn closures_convert_their_arguments() {
+    let mut engine = Engine::new();
+    engine.register("longer", |n: f64, s: String| s.len() as f64 > n).unwrap();
+    engine.register("greet", |name: Option<String>| format!("hello {}", name.unwrap_or_else(|| "you".into()))).unwrap();
+    engine.register("sum", |xs: Vec<f64>| xs.iter().sum::<f64>()).unwrap();
+    engine.register("scale", |by: f64, xs: Vec<f64>| xs.iter().map(|x| x * by).sum::<f64>()).unwrap();
+    engine.register("nothing", || ()).unwrap();
+
+    assert!(engine.eval("longer(3, \"lox!\");").unwrap() == Atom::Bool(true));
+    assert!(engine.eval("greet(\"lox\");").unwrap() == Atom::String("hello lox".into()));
+    assert!(engine.eval("greet(nil);").unwrap() == Atom::String("hello you".into()));
+    assert!(engine.eval("sum();").unwrap() == Atom::Number(0.0));
+    assert!(engine.eval("sum(1, 2, 3);").unwrap() == Atom::Number(6.0));
+    assert!(engine.eval("scale(2, 1, 2);").unwrap() == Atom::Number(6.0));
+    assert!(engine.eval("nothing();").unwrap() == Atom::Nil);
+    //natives are values like any other function
+    assert!(engine.eval("var f = sum; f(4);").unwrap() == Atom::Number(4.0));
+}
+
+#[test]
+fn mismatches_are_runtime_errors() {
+    let mut engine = Engine::new();
+    engine.register("longer", |n: f64, s: String| s.len() as f64 > n).unwrap();
+    engine.register("scale", |by: f64, xs: Vec<f64>| xs.iter().map(|x| x * by).sum::<f64>()).unwrap();
+
+    assert_eq!(error(&mut engine, "longer(1);"), "RuntimeError: Expected 2 arguments but got 1");
+    assert_eq!(error(&mut engine, "scale();"), "RuntimeError: Expected at least 1 arguments but got 0");
+    assert_eq!(error(&mut engine, "longer(\"1\", \"2\");"), "RuntimeError: longer: argument 1 should be a Number but got String");
+    assert_eq!(error(&mut engine, "scale(1, 2, true);"), "RuntimeError: scale: argument 3 should be a Number but got Bool");
+    assert_eq!(error(&mut engine, "clock(1);"), "RuntimeError: Expected 0 arguments but got 1");
+}
+
+#[test]
+fn vecs_are_returned_as_lists() {
+    let mut engine = Engine::new();
+    engine.register("words", |s: String| s.split(' ').map(String::from).collect::<Vec<String>>()).unwrap();
+
+    assert!(engine.eval("var w = words(\"a bc d\"); w.length;").unwrap() == Atom::Number(3.0));
+    assert!(engine.eval("w.get(1);").unwrap() == Atom::String("bc".into()));
+    assert!(engine.eval("typeof(w);").unwrap() == Atom::String("List".into()));
+    assert_eq!(error(&mut engine, "w.get(3);"), "RuntimeError: List index 3 is out of range for length 3");
+}
+
+#[test]
+fn vec_parameters_must_come_last() {
+    let mut engine = Engine::new();
+    let err = engine.register("bad", |xs: Vec<f64>, by: f64| xs.len() as f64 * by).unwrap_err();
+    assert_eq!(err.to_string(), "TypeError: bad: only the last parameter can take the rest of the arguments, not parameter 1");
+    assert_eq!(error(&mut engine, "bad;"), "RuntimeError: Undefined variable bad");
+}
+
+#[test]
+fn lox_can_pass_natives_back() {
+    let mut engine = Engine::new();
+    engine.register("twice", |x: f64| x * 2.0).unwrap();
+    engine.eval("fun apply(f, x) { return f(x); }").unwrap();
+
+    let apply = engine.get_global("apply").unwrap();
+    let twice = engine.get_global("twice").unwrap();
+    assert!(engine.call(&apply, vec![twice, Atom::Number(21.0)]).unwrap() == Atom::Number(42.0));
+}
@@ -0,0 +1,79 @@
+extern crate lox_rs;
+
+use lox_rs::lox::engine::Engine;
+use lox_rs::lox::errors::LanguageError;
+use lox_rs::lox::output::Buffer;
+
+use std::io::{self, Write};
+
+#[test]
+fn print_goes_to_the_sink() {
+    let out = Buffer::new();
+    let mut engine = Engine::new();
+    engine.set_stdout(out.clone());
+
+    engine.eval("print 1 + 2; print \"lox\";").unwrap();
+    assert_eq!(out.contents(), "3\nlox\n");
+
+    assert_eq!(out.take(), "3\nlox\n");
+    engine.eval("print nil;").unwrap();
+    assert_eq!(out.contents(), "nil\n");
+}
+
+#[test]
+fn engines_keep_their_output_apart() {
+    let (first, second) = (Buffer::new(), Buffer::new());
+    let mut a = Engine::new();
+    let mut b = Engine::new();
+    a.set_stdout(first.clone());
+    b.set_stdout(second.clone());
+
+    a.eval("print 1;").unwrap();
+    b.eval("print 2;").unwrap();
+    assert_eq!(first.contents(), "1\n");
+    assert_eq!(second.contents(), "2\n");
+}
+
+#[test]
+fn errors_and_traces_go_to_stderr() {
+    let (out, err) = (Buffer::new(), Buffer::new());
+    let mut engine = Engine::new();
+    engine.set_stdout(out.clone());
+    engine.trace();
+    //the trace follows stderr when it is replaced later
+    engine.set_stderr(err.clone());
+
+    engine.eval("print true;").unwrap();
+    let failed = engine.eval("nope;").unwrap_err();
+    engine.report(&failed);
+    assert_eq!(out.contents(), "true\n");
+    assert_eq!(err.contents(), "\
+[line 1] Print
+[line 1]   Literal = true
+[line 1] Expression
+[line 1] !! RuntimeError: Undefined variable nope
+RuntimeError(\"Undefined variable nope\")
+");
+}
+
+struct Closed;
+
+impl Write for Closed {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn failed_writes_stop_the_program() {
+    let mut engine = Engine::new();
+    engine.set_stdout(Closed);
+
+    let err = engine.eval("print 1; var after = 2;").unwrap_err();
+    assert!(matches!(err.errors.as_slice(), [LanguageError::IoError(_)]));
+    assert!(engine.get_global("after").is_none());
+}
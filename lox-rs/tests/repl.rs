@@ -0,0 +1,55 @@
+extern crate lox_rs;
+
+use lox_rs::lox::engine;
+use lox_rs::lox::errors::LanguageError;
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn errors(source: &str) -> Vec<LanguageError> {
+    engine::parse(source).err().map_or(Vec::new(), |err| err.errors)
+}
+
+fn is_incomplete(source: &str) -> bool {
+    let errs = errors(source);
+    !errs.is_empty() && errs.iter().all(|err| matches!(err, LanguageError::IncompleteInput(_)))
+}
+
+#[test]
+fn unfinished_input_is_incomplete() {
+    for source in ["fun f(a,", "fun f(a) {", "{ print 1;", "print (1 +", "var s = \"open", "/* open", "print 1", "var"] {
+        assert!(is_incomplete(source), "{:?} gave {:?}", source, errors(source));
+    }
+}
+
+#[test]
+fn broken_input_is_an_error() {
+    for source in ["print );", "}", "print 1 +;", "1 = 2;", "return 1;"] {
+        let errs = errors(source);
+        assert!(!errs.is_empty() && !is_incomplete(source), "{:?} gave {:?}", source, errs);
+    }
+}
+
+fn repl(input: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lox-rs"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn prompt_continues_until_input_is_complete() {
+    let output = repl("fun add(a,\n b) {\n  return a + b;\n}\nprint add(1,\n 2);\n");
+    assert_eq!(output, ">.........>...3\n>\n");
+}
+
+#[test]
+fn error_resets_the_prompt() {
+    let output = repl("print );\nprint 1;\n");
+    assert_eq!(output, ">>1\n>\n");
+}
@@ -0,0 +1,93 @@
+extern crate lox_rs;
+
+use lox_rs::lox::atom::Atom;
+use lox_rs::lox::engine::Engine;
+use lox_rs::lox::errors::LanguageError;
+use lox_rs::lox::sandbox::Limits;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+//takes about 2^n steps
+const EXPONENTIAL: &str = "fun slow(n) { return n < 1 ? 0 : slow(n - 1) + slow(n - 1); }";
+
+fn limit_error(engine: &mut Engine, source: &str) -> String {
+    match engine.eval(source).unwrap_err().errors.as_slice() {
+        [LanguageError::LimitError(msg)] => msg.clone(),
+        other => panic!("expected a limit error, got {:?}", other)
+    }
+}
+
+#[test]
+fn step_budget() {
+    let mut engine = Engine::new();
+    engine.eval(EXPONENTIAL).unwrap();
+    engine.set_limits(Limits { max_steps: Some(10_000), ..Limits::default() });
+
+    assert_eq!(limit_error(&mut engine, "slow(30);"), "Step limit of 10000 reached");
+    //the budget is per run
+    assert!(engine.eval("slow(3);").unwrap() == Atom::Number(0.0));
+}
+
+#[test]
+fn time_limit() {
+    let mut engine = Engine::new();
+    engine.eval(EXPONENTIAL).unwrap();
+    engine.set_limits(Limits { timeout: Some(Duration::from_millis(50)), ..Limits::default() });
+
+    let started = Instant::now();
+    assert_eq!(limit_error(&mut engine, "slow(40);"), "Time limit of 50ms reached");
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn memory_cap() {
+    let mut engine = Engine::new();
+    engine.eval("fun grow(s, n) { return n < 1 ? s : grow(s + s, n - 1); }").unwrap();
+    engine.set_limits(Limits { max_memory: Some(1 << 16), ..Limits::default() });
+
+    assert!(engine.eval("grow(\"ab\", 10);").is_ok());
+    assert_eq!(limit_error(&mut engine, "grow(\"ab\", 20);"), "Memory limit of 65536 bytes reached");
+
+    //strings from natives count too
+    engine.register("repeat", |s: String, n: f64| s.repeat(n as usize)).unwrap();
+    engine.set_limits(Limits { max_memory: Some(1 << 10), ..Limits::default() });
+    assert!(engine.eval("repeat(\"x\", 1000);").is_ok());
+    assert_eq!(limit_error(&mut engine, "repeat(\"x\", 2000);"), "Memory limit of 1024 bytes reached");
+}
+
+#[test]
+fn call_depth() {
+    let mut engine = Engine::new();
+    engine.eval("fun down(n) { return n < 1 ? 0 : down(n - 1); }").unwrap();
+    engine.set_limits(Limits { max_call_depth: Some(20), ..Limits::default() });
+
+    assert!(engine.eval("down(19);").is_ok());
+    assert_eq!(limit_error(&mut engine, "down(20);"), "Call depth limit of 20 reached");
+
+    let down = engine.get_global("down").unwrap();
+    let err = engine.call(&down, vec![Atom::Number(50.0)]).unwrap_err();
+    assert!(matches!(err.errors.as_slice(), [LanguageError::LimitError(_)]));
+}
+
+#[test]
+fn cancel_from_another_thread() {
+    let mut engine = Engine::new();
+    engine.eval(EXPONENTIAL).unwrap();
+    let handle = engine.cancel_handle();
+
+    let canceller = thread::spawn({
+        let handle = handle.clone();
+        move || {
+            thread::sleep(Duration::from_millis(50));
+            handle.cancel();
+        }
+    });
+    assert_eq!(limit_error(&mut engine, "slow(40);"), "Cancelled");
+    canceller.join().unwrap();
+
+    //a cancel after the run ended doesn't stop the next one
+    handle.cancel();
+    assert!(engine.eval("1;").unwrap() == Atom::Number(1.0));
+    assert!(!handle.is_cancelled());
+}
@@ -0,0 +1,18 @@
+extern crate lox_rs;
+
+use lox_rs::lox::scanner::{Scanner, TokenType};
+
+#[test]
+fn unexpected_characters_are_syntax_errors() {
+    let mut scanner = Scanner::new("1 ~ 2;\nvar # = 3;".into());
+    let errors: Vec<String> = scanner.scan_tokens().unwrap_err().iter().map(|err| err.to_string()).collect();
+
+    assert_eq!(errors, [
+        "SyntaxError: Unexpected character \"~\" on line 1",
+        "SyntaxError: Unexpected character \"#\" on line 2"
+    ]);
+    //the bad characters are skipped and everything around them still scans
+    let eof = scanner.tokens().last().unwrap();
+    assert_eq!((&eof.token_type, eof.lexeme.as_str()), (&TokenType::EOF, ""));
+    assert_eq!(scanner.tokens().len(), 8);
+}
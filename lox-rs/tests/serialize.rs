@@ -0,0 +1,145 @@
+extern crate lox_rs;
+
+use lox_rs::lox::ast::{Ast, Expr, Interpreter, Span, Stmt};
+use lox_rs::lox::atom::Atom;
+use lox_rs::lox::engine;
+use lox_rs::lox::json::{self, Json};
+use lox_rs::lox::resolver;
+use lox_rs::lox::sexp::{self, Sexp};
+use lox_rs::lox::symbol::Symbol;
+
+use std::rc::Rc;
+
+//every kind of node at least once
+const SOURCE: &str = "/// the answer
+var answer = 40;
+fun add(a, b) { return a + b; }
+var square = (x) => x ** 2;
+var twice = fun (f, x) { { var once = f(x); return f(once); } };
+answer += (true ? 2 : nil);
+var result = twice(square, 3) ~/ 2, add(answer, 1);
+var text = \"two
+lines\";
+print result;
+";
+
+#[test]
+fn json_round_trip() {
+    let (ast, stmts) = engine::parse(SOURCE).unwrap();
+    let written = json::program(&ast, &stmts).to_string();
+
+    let (loaded, loaded_stmts) = json::load(&Json::parse(&written).unwrap()).unwrap();
+    assert_eq!(json::program(&loaded, &loaded_stmts).to_string(), written);
+}
+
+#[test]
+fn sexp_round_trip() {
+    let (ast, stmts) = engine::parse(SOURCE).unwrap();
+    let program = json::program(&ast, &stmts);
+    let written = Sexp(&program).to_string();
+
+    let (loaded, loaded_stmts) = json::load(&sexp::parse(&written).unwrap()).unwrap();
+    assert_eq!(json::program(&loaded, &loaded_stmts), program);
+}
+
+#[test]
+fn loaded_program_runs() {
+    let (ast, stmts) = engine::parse(SOURCE).unwrap();
+    let written = json::program(&ast, &stmts).to_string();
+    let (mut loaded, stmts) = json::load(&Json::parse(&written).unwrap()).unwrap();
+    resolver::resolve(&mut loaded, &stmts).unwrap();
+
+    let mut intp = Interpreter::default();
+    intp.interpret(&Rc::new(loaded), &stmts);
+    assert!(intp.error.is_none());
+    assert_eq!(intp.globals.borrow().get(&Symbol::intern("answer")), Atom::Number(42.0));
+    assert_eq!(intp.globals.borrow().get(&Symbol::intern("result")), Atom::Number(43.0));
+}
+
+#[test]
+fn hand_written_programs_load() {
+    let program = Json::parse(r#"[{"type": "Var", "name": "x", "initializer":
+        {"type": "Binary", "left": {"type": "Literal", "value": 1},
+         "operator": {"type": "STAR", "lexeme": "*"}, "right": {"type": "Literal", "value": 2}}}]"#).unwrap();
+    assert!(json::load(&program).is_ok());
+
+    let program = sexp::parse(r#"((Expression :expression (Unary :operator (BANG :lexeme "!") :right (Literal :value true))))"#).unwrap();
+    assert!(json::load(&program).is_ok());
+
+    let wrong_lexeme = sexp::parse(r#"((Expression :expression (Binary :left (Literal :value 1) :operator (PLUS :lexeme "-") :right (Literal :value 2))))"#).unwrap();
+    assert!(json::load(&wrong_lexeme).is_err());
+}
+
+fn load_error(program: &str) -> String {
+    json::load(&sexp::parse(program).unwrap()).err().unwrap()
+}
+
+#[test]
+fn operators_must_fit_their_node() {
+    assert_eq!(load_error(r#"((Expression :expression (Binary :left (Literal :value 1) :operator (EQUAL :lexeme "=") :right (Literal :value 2))))"#),
+               "\"=\" is not a binary operator");
+    assert_eq!(load_error(r#"((Expression :expression (Unary :operator (PLUS :lexeme "+") :right (Literal :value 2))))"#),
+               "\"+\" is not a unary operator");
+    assert_eq!(load_error(r#"((Expression :expression (Assign :name "x" :operator (LESS :lexeme "<") :value (Literal :value 2))))"#),
+               "\"<\" is not an assignment operator");
+}
+
+#[test]
+fn names_must_be_identifiers() {
+    assert_eq!(load_error(r#"((Expression :expression (Var :name "not a name")))"#), "Invalid name \"not a name\"");
+    assert_eq!(load_error(r#"((Expression :expression (Var :name "class")))"#), "Invalid name \"class\"");
+}
+
+#[test]
+fn return_outside_a_function_is_rejected() {
+    assert_eq!(load_error("((Return :value (Literal :value 1)))"), "Can't return from top-level code");
+    assert!(json::load(&sexp::parse("((Function :name \"f\" :params () :body ((Return :value (Literal :value 1)))))").unwrap()).is_ok());
+}
+
+#[test]
+fn deep_nesting_is_an_error() {
+    let depth = 200_000;
+    assert!(Json::parse(&format!("{}{}", "[".repeat(depth), "]".repeat(depth))).is_err());
+    assert!(sexp::parse(&format!("{}{}", "(".repeat(depth), ")".repeat(depth))).is_err());
+
+    let mut expr = String::from("(Literal :value 1)");
+    for _ in 0..300 {
+        expr = format!("(Grouping :expression {})", expr);
+    }
+    assert_eq!(load_error(&format!("((Expression :expression {}))", expr)), "Nesting deeper than 256 levels");
+}
+
+#[test]
+fn non_finite_numbers_round_trip() {
+    let (ast, stmts) = engine::parse("print 1 / 0;").unwrap();
+    let mut folded = Ast::default();
+    let value = folded.add_expr(Expr::Literal(Atom::Number(f64::NEG_INFINITY)), Span::default());
+    let stmt = folded.add_stmt(Stmt::Print(value), Span::default());
+    let written = json::program(&folded, &[stmt]).to_string();
+    assert!(written.contains(r#"{"number":"-Infinity"}"#));
+
+    let (loaded, loaded_stmts) = json::load(&Json::parse(&written).unwrap()).unwrap();
+    assert_eq!(json::program(&loaded, &loaded_stmts).to_string(), written);
+    assert!(json::load(&json::program(&ast, &stmts)).is_ok());
+}
+
+#[test]
+fn unicode_escapes_are_checked() {
+    assert_eq!(Json::parse(r#""\u00e9\ud83d\ude00""#).unwrap(), Json::String("é😀".into()));
+    assert!(Json::parse(r#""\u+123""#).is_err());
+    assert!(Json::parse(r#""\u12""#).is_err());
+    //a high surrogate needs a low one after it, and a low one a high one
+    assert!(Json::parse(r#""\ud83d""#).is_err());
+    assert!(Json::parse(r#""\ud83dx""#).is_err());
+    assert!(Json::parse(r#""\ud83d\ud83d""#).is_err());
+    assert!(Json::parse(r#""\ud83d\uffff""#).is_err());
+    assert!(Json::parse(r#""\ude00""#).is_err());
+}
+
+#[test]
+fn empty_arrays_and_objects_stay_apart() {
+    let both = Json::Array(vec![Json::Array(Vec::new()), Json::Object(Vec::new())]);
+    let written = Sexp(&both).to_string();
+    assert_eq!(written, "(() (:))");
+    assert_eq!(sexp::parse(&written).unwrap(), both);
+}
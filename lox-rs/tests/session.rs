@@ -0,0 +1,118 @@
+extern crate lox_rs;
+
+use lox_rs::lox::atom::Atom;
+use lox_rs::lox::engine::Engine;
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::{env, fs};
+
+fn temp(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("lox-session-{}-{}.lox-state", name, std::process::id()))
+}
+
+#[test]
+fn globals_survive_a_new_engine() {
+    let path = temp("engine");
+    let mut engine = Engine::new();
+    engine.eval("
+        var count = 3;
+        var label = \"two
+lines\";
+        var ready = true;
+        var nothing;
+        var big = -1 / 0;
+        fun add(a, b) { return a + b; }
+        var double = (x) => x * 2;
+        var alias = add;
+        var tick = clock;
+    ").unwrap();
+    engine.save_session(&path).unwrap();
+
+    let mut restored = Engine::new();
+    restored.load_session(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    for name in ["count", "label", "ready", "nothing", "big"] {
+        assert!(restored.get_global(name) == engine.get_global(name), "{} differs", name);
+    }
+    assert!(restored.get_global("nothing") == Some(Atom::Nil));
+    assert!(restored.eval("add(count, double(2)) + alias(1, 1);").unwrap() == Atom::Number(9.0));
+    assert!(restored.eval("typeof(tick);").unwrap() == Atom::String("Function".into()));
+}
+
+#[test]
+fn what_cannot_be_saved_is_noted() {
+    let path = temp("notes");
+    let mut engine = Engine::new();
+    engine.eval("fun counter() { var n = 0; return () => n += 1; } var next = counter();").unwrap();
+    engine.save_session(&path).unwrap();
+    let saved = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(saved.starts_with("//rlox session\n"));
+    assert!(saved.contains("//next closed over local variables, they are not saved\nvar next = fun () {"));
+    //builtin natives are the host's to define
+    assert!(!saved.contains("clock"));
+}
+
+#[test]
+fn saving_to_a_bad_path_is_an_io_error() {
+    let err = Engine::new().save_session("/nonexistent/dir/state").unwrap_err();
+    assert_eq!(err.errors[0].kind(), "IoError");
+}
+
+fn repl(args: &[&str], input: &str) -> (String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lox-rs"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    (String::from_utf8(output.stdout).unwrap(), String::from_utf8(output.stderr).unwrap())
+}
+
+#[test]
+fn repl_save_and_restore() {
+    let path = temp("repl");
+    let file = path.to_str().unwrap();
+
+    let (_, err) = repl(&[], &format!("var total = 40;\nfun bump(n) {{\n  return total + n;\n}}\n:save {}\n", file));
+    assert_eq!(err, "");
+
+    let (out, err) = repl(&["--restore", file], "print bump(2);\n");
+    assert_eq!((out.as_str(), err.as_str()), (">42\n>\n", ""));
+
+    let (out, _) = repl(&[], &format!(":load {}\nprint total;\n", file));
+    assert_eq!(out, ">>40\n>\n");
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn restored_sessions_can_be_traced() {
+    let path = temp("traced");
+    let file = path.to_str().unwrap();
+    repl(&[], &format!("fun twice(n) {{ return n * 2; }}\n:save {}\n", file));
+
+    for args in [["--trace", "--restore", file], ["--restore", file, "--trace"]] {
+        let (out, err) = repl(&args, "print twice(2);\n");
+        assert_eq!(out, ">4\n>\n");
+        assert!(err.starts_with("[line 1] Print\n"), "{}", err);
+        assert!(err.contains("[line 1] <- <fn twice> = 4\n"), "{}", err);
+    }
+
+    let (_, err) = repl(&["--restore", file, "script.lox"], "");
+    assert!(err.starts_with("Usage:"));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn repl_command_errors() {
+    let (out, err) = repl(&[], ":save\n:nope\nprint 1;\n");
+    assert_eq!(out, ">>>1\n>\n");
+    assert_eq!(err, "IoError(\":save expects a file\")\nSyntaxError(\"Unknown command :nope, expected :save or :load\")\n");
+}
@@ -0,0 +1,44 @@
+extern crate lox_rs;
+
+use lox_rs::lox::atom::Atom;
+use lox_rs::lox::engine::Engine;
+use lox_rs::lox::output::Buffer;
+
+fn printed(source: &str) -> String {
+    let out = Buffer::new();
+    let mut engine = Engine::new();
+    engine.set_stdout(out.clone());
+    engine.eval(source).unwrap();
+    out.contents()
+}
+
+#[test]
+fn print_shows_values_as_lox_writes_them() {
+    assert_eq!(printed("print 1 + 2; print 2.5; print -0.5; print 1 / 0;"), "3\n2.5\n-0.5\nInfinity\n");
+    assert_eq!(printed("print nil; print true; print !true;"), "nil\ntrue\nfalse\n");
+    assert_eq!(printed("print \"a \" + \"string\";"), "a string\n");
+    assert_eq!(printed("fun f() {} print f; print (x) => x; print clock;"), "<fn f>\n<fn>\n<native fn clock>\n");
+}
+
+#[test]
+fn str_and_typeof() {
+    let mut engine = Engine::new();
+    let eval = |engine: &mut Engine, source: &str| engine.eval(source).unwrap();
+
+    assert!(eval(&mut engine, "str(3);") == Atom::String("3".into()));
+    assert!(eval(&mut engine, "str(nil) + str(false);") == Atom::String("nilfalse".into()));
+    assert!(eval(&mut engine, "str(\"s\");") == Atom::String("s".into()));
+
+    for (source, name) in [("1", "Number"), ("\"s\"", "String"), ("true", "Bool"), ("nil", "Nil"), ("clock", "Function"), ("(x) => x", "Function")] {
+        assert!(eval(&mut engine, &format!("typeof({});", source)) == Atom::String(name.into()));
+    }
+}
+
+#[test]
+fn errors_still_name_the_type() {
+    let mut engine = Engine::new();
+    let err = engine.eval("1 + nil;").unwrap_err();
+    assert_eq!(err.to_string(), "TypeError: Addition not supported for Number and Nil");
+    let err = engine.eval("\"f\"();").unwrap_err();
+    assert_eq!(err.to_string(), "TypeError: String is not callable");
+}
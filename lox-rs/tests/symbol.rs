@@ -0,0 +1,50 @@
+extern crate lox_rs;
+
+use lox_rs::lox::atom::Atom;
+use lox_rs::lox::engine::Engine;
+use lox_rs::lox::symbol::Symbol;
+
+use std::thread;
+
+#[test]
+fn same_name_same_symbol() {
+    let a = Symbol::intern("alpha");
+    assert_eq!(a, Symbol::intern("alpha"));
+    assert_ne!(a, Symbol::intern("beta"));
+    assert_eq!(a.name(), "alpha");
+    assert_eq!(format!("{} {:?}", a, a), "alpha \"alpha\"");
+}
+
+#[test]
+fn each_thread_has_its_own_table() {
+    let here = Symbol::intern("only_here");
+    let there = thread::spawn(|| {
+        let first = Symbol::intern("there_first");
+        (first.to_string(), Symbol::intern("only_here").to_string())
+    }).join().unwrap();
+
+    assert_eq!(there, ("there_first".to_string(), "only_here".to_string()));
+    assert_eq!(here.to_string(), "only_here");
+}
+
+#[test]
+fn unused_names_are_reclaimed() {
+    let kept = Symbol::intern("kept");
+    for i in 0..100_000 {
+        Symbol::intern(&format!("name{}", i));
+    }
+    assert!(Symbol::interned() < 1000, "{} names interned", Symbol::interned());
+    assert_eq!(kept, Symbol::intern("kept"));
+    assert_eq!(kept.to_string(), "kept");
+}
+
+#[test]
+fn names_of_finished_scripts_are_reclaimed() {
+    let mut engine = Engine::new();
+    engine.eval("var total = 0;").unwrap();
+    for i in 0..10_000 {
+        engine.eval(&format!("{{ var local{} = {}; total = total + local{}; }}", i, i, i)).unwrap();
+    }
+    assert!(Symbol::interned() < 1000, "{} names interned", Symbol::interned());
+    assert_eq!(engine.get_global("total"), Some(Atom::Number(49995000.0)));
+}
@@ -0,0 +1,156 @@
+extern crate lox_rs;
+
+use lox_rs::lox::ast::{Ast, ExprId, Span, StmtId};
+use lox_rs::lox::atom::Atom;
+use lox_rs::lox::engine::Engine;
+use lox_rs::lox::errors::LanguageError;
+use lox_rs::lox::output::Buffer;
+use lox_rs::lox::trace::{Hook, Tracer};
+
+use std::cell::RefCell;
+use std::fs;
+use std::process::Command;
+use std::rc::Rc;
+
+//everything but expressions, which are too many to list
+#[derive(Clone, Default)]
+struct Recorder {
+    events: Rc<RefCell<Vec<String>>>,
+    values: Rc<RefCell<Vec<(i32, String)>>>
+}
+
+impl Hook for Recorder {
+    fn statement(&mut self, _ast: &Ast, _id: StmtId, span: Span) {
+        self.events.borrow_mut().push(format!("stmt {}", span.line));
+    }
+
+    fn expression(&mut self, _ast: &Ast, _id: ExprId, span: Span, value: &Atom) {
+        self.values.borrow_mut().push((span.line, value.to_string()));
+    }
+
+    fn enter_function(&mut self, callee: &Atom, args: &[Atom], span: Span) {
+        self.events.borrow_mut().push(format!("enter {} {} {}", callee, args.len(), span.line));
+    }
+
+    fn exit_function(&mut self, callee: &Atom, value: &Atom, span: Span) {
+        self.events.borrow_mut().push(format!("exit {} {} {}", callee, value, span.line));
+    }
+
+    fn error(&mut self, err: &LanguageError, span: Span) {
+        self.events.borrow_mut().push(format!("error {} {}", err.kind(), span.line));
+    }
+}
+
+const PROGRAM: &str = "fun add(a, b) {
+    return a + b;
+}
+var x = add(1, 2);
+str(x);
+";
+
+#[test]
+fn statements_calls_and_values() {
+    let recorder = Recorder::default();
+    let mut engine = Engine::new();
+    engine.set_hook(recorder.clone());
+    engine.eval(PROGRAM).unwrap();
+
+    assert_eq!(*recorder.events.borrow(), [
+        "stmt 1", "stmt 4",
+        "enter <fn add> 2 4", "stmt 2", "exit <fn add> 3 4",
+        "stmt 5",
+        "enter <native fn str> 1 5", "exit <native fn str> 3 5"
+    ]);
+    let values = recorder.values.borrow();
+    assert!(values.contains(&(2, String::from("3"))));
+    assert_eq!(values.last(), Some(&(5, String::from("3"))));
+}
+
+#[test]
+fn errors_are_reported_once_where_they_happen() {
+    let recorder = Recorder::default();
+    let mut engine = Engine::new();
+    engine.set_hook(recorder.clone());
+    engine.eval("fun add(a, b) {\n    return a + b;\n}\nprint add(1, nil);\nprint 2;").unwrap_err();
+
+    assert_eq!(*recorder.events.borrow(), [
+        "stmt 1", "stmt 4",
+        "enter <fn add> 2 4", "stmt 2", "error TypeError 2", "exit <fn add> nil 4"
+    ]);
+
+    //a later run reports its own error again
+    recorder.events.borrow_mut().clear();
+    engine.eval("nope;").unwrap_err();
+    assert_eq!(*recorder.events.borrow(), ["stmt 1", "error RuntimeError 1"]);
+}
+
+#[test]
+fn host_calls_are_traced_without_a_position() {
+    let recorder = Recorder::default();
+    let mut engine = Engine::new();
+    engine.eval("fun add(a, b) {\n    return a + b;\n}").unwrap();
+    engine.set_hook(recorder.clone());
+    let add = engine.get_global("add").unwrap();
+    engine.call(&add, vec![Atom::Number(1.0), Atom::Number(2.0)]).unwrap();
+
+    assert_eq!(*recorder.events.borrow(), ["enter <fn add> 2 0", "stmt 2", "exit <fn add> 3 0"]);
+}
+
+#[test]
+fn hooks_can_be_removed() {
+    let recorder = Recorder::default();
+    let mut engine = Engine::new();
+    engine.set_hook(recorder.clone());
+    engine.interpreter().clear_hook();
+    engine.eval(PROGRAM).unwrap();
+
+    assert!(recorder.events.borrow().is_empty());
+    assert!(recorder.values.borrow().is_empty());
+}
+
+#[test]
+fn tracer_logs_each_event() {
+    let out = Buffer::new();
+    let mut engine = Engine::new();
+    engine.set_hook(Tracer::new(out.clone()));
+    engine.eval("fun twice(n) { return n * 2; }\ntwice(4);").unwrap();
+
+    assert_eq!(out.contents(), "\
+[line 1] Function
+[line 2] Expression
+[line 2]   Var = <fn twice>
+[line 2]   Literal = 4
+[line 2] -> <fn twice>(4)
+[line 1]   Return
+[line 1]     Var = 4
+[line 1]     Literal = 2
+[line 1]     Binary = 8
+[line 2] <- <fn twice> = 8
+[line 2]   Call = 8
+");
+}
+
+#[test]
+fn trace_flag() {
+    let path = std::env::temp_dir().join(format!("lox-trace-{}.lox", std::process::id()));
+    fs::write(&path, "print 1 + 1;\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_lox-rs")).arg("--trace").arg(&path).output().unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "2\n");
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "\
+[line 1] Print
+[line 1]   Literal = 1
+[line 1]   Literal = 1
+[line 1]   Binary = 2
+");
+}
+
+#[test]
+fn trace_flag_takes_one_script() {
+    let output = Command::new(env!("CARGO_BIN_EXE_lox-rs")).args(["--trace", "a.lox", "b.lox"]).output().unwrap();
+
+    assert_eq!(output.status.code(), Some(64));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("rlox [--trace] [script]"));
+}
@@ -0,0 +1,67 @@
+extern crate lox_rs;
+
+use lox_rs::lox::ast::{Ast, AstPrinter, Expr, ExprId, StmtId};
+use lox_rs::lox::atom::Atom;
+use lox_rs::lox::engine;
+use lox_rs::lox::fold::{self, Fold};
+use lox_rs::lox::scanner::{Token, TokenType};
+use lox_rs::lox::symbol::Symbol;
+use lox_rs::lox::visit::Visitor;
+
+fn print(ast: &Ast, stmts: &[StmtId]) -> Vec<String> {
+    let mut printer = AstPrinter::new(ast);
+    stmts.iter().map(|stmt| printer.print_stmt(*stmt)).collect()
+}
+
+//only overrides the one node kind it needs
+#[derive(Default)]
+struct Vars(Vec<String>);
+
+impl<'ast> Visitor<'ast> for Vars {
+    fn visit_var(&mut self, _ast: &'ast Ast, _id: ExprId, name: &'ast Symbol) {
+        self.0.push(name.to_string());
+    }
+}
+
+#[test]
+fn visitor_reaches_nested_nodes() {
+    let (ast, stmts) = engine::parse("var f = (a) => { print a + b; }; f(c ? d : -e);").unwrap();
+    let mut vars = Vars::default();
+    for stmt in stmts.iter() {
+        vars.visit_stmt(&ast, *stmt);
+    }
+
+    assert_eq!(vars.0, vec!["a", "b", "f", "c", "d", "e"]);
+}
+
+//folds additions of two number literals
+struct ConstantFold;
+
+impl Fold for ConstantFold {
+    fn fold_binary(&mut self, ast: &mut Ast, id: ExprId, lhs: ExprId, op: Token, rhs: ExprId) -> ExprId {
+        let (new_lhs, new_rhs) = (self.fold_expr(ast, lhs), self.fold_expr(ast, rhs));
+        match (ast.expr(new_lhs), &op.token_type, ast.expr(new_rhs)) {
+            (Expr::Literal(Atom::Number(a)), TokenType::PLUS, Expr::Literal(Atom::Number(b))) => {
+                let sum = Expr::Literal(Atom::Number(a + b));
+                fold::replace_expr(ast, id, true, sum)
+            },
+            _ => fold::replace_expr(ast, id, (new_lhs, new_rhs) != (lhs, rhs), Expr::Binary(new_lhs, op, new_rhs))
+        }
+    }
+}
+
+#[test]
+fn fold_rewrites_and_keeps_untouched_nodes() {
+    let (mut ast, stmts) = engine::parse("print 1 + 2 + x; fun f() { return 3 + 4; } print y;").unwrap();
+    let folded: Vec<StmtId> = stmts.iter().map(|stmt| ConstantFold.fold_stmt(&mut ast, *stmt)).collect();
+
+    assert_eq!(print(&ast, &folded), vec![
+        "(print [(+) Number(3.0) x] )",
+        "(fun f () (return Number(7.0) ) )",
+        "(print y )"
+    ]);
+    assert_ne!(folded[0], stmts[0]);
+    assert_eq!(folded[2], stmts[2]);
+    //the original tree is still there
+    assert_eq!(print(&ast, &stmts[..1]), vec!["(print [(+) [(+) Number(1.0) Number(2.0)] x] )"]);
+}